@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::scheduler;
+use crate::storage;
+
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    file: String,
+    size: u64,
+}
+
+enum Op {
+    Write(PathBuf, Arc<Vec<u8>>),
+    Remove(PathBuf),
+    // Saved once nothing else is queued, so that a burst of changes writes it once
+    Index(PathBuf, VecDeque<Entry>),
+}
+
+// Files are written and removed by a thread of its own, so that callers never wait on writes.
+// Reads stay synchronous, audio of a tweet is small. Data not written yet is kept to be read meanwhile.
+struct Writer {
+    tx: std::sync::mpsc::Sender<Op>,
+    pending: Arc<Mutex<HashMap<PathBuf, Arc<Vec<u8>>>>>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl Writer {
+    fn start() -> Self {
+        let (tx, rx) = std::sync::mpsc::channel::<Op>();
+        let pending = Arc::new(Mutex::new(HashMap::<PathBuf, Arc<Vec<u8>>>::new()));
+
+        let pending_c = pending.clone();
+        let thread = std::thread::spawn(move || {
+            let mut index: Option<(PathBuf, VecDeque<Entry>)> = None;
+
+            // Ends once the cache is dropped and everything queued is done
+            loop {
+                let op = match rx.try_recv() {
+                    Ok(op) => op,
+                    Err(std::sync::mpsc::TryRecvError::Empty) => {
+                        if let Some((path, entries)) = index.take() {
+                            save_index(&path, &entries);
+                        }
+                        match rx.recv() {
+                            Ok(op) => op,
+                            Err(_) => break,
+                        }
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => break,
+                };
+
+                match op {
+                    Op::Write(path, data) => {
+                        let written = path
+                            .parent()
+                            .map_or(Ok(()), std::fs::create_dir_all)
+                            .and_then(|_| std::fs::write(&path, data.as_slice()));
+                        if let Err(e) = written {
+                            println!("audio_cache: failed to write {:?} {:?}", path, e);
+                        }
+
+                        // Unless written again meanwhile
+                        let mut pending = pending_c.lock().unwrap();
+                        if pending.get(&path).map_or(false, |d| Arc::ptr_eq(d, &data)) {
+                            pending.remove(&path);
+                        }
+                    }
+
+                    Op::Remove(path) => {
+                        let _ = std::fs::remove_file(path);
+                    }
+
+                    Op::Index(path, entries) => index = Some((path, entries)),
+                }
+            }
+
+            if let Some((path, entries)) = index.take() {
+                save_index(&path, &entries);
+            }
+        });
+
+        Writer { tx, pending, thread }
+    }
+
+    fn send(&self, op: Op) {
+        if let Op::Write(ref path, ref data) = op {
+            self.pending.lock().unwrap().insert(path.clone(), data.clone());
+        }
+        if let Op::Remove(ref path) = op {
+            self.pending.lock().unwrap().remove(path);
+        }
+
+        let _ = self.tx.send(op);
+    }
+
+    fn read(&self, path: &std::path::Path) -> std::io::Result<Vec<u8>> {
+        match self.pending.lock().unwrap().get(path) {
+            Some(data) => Ok(data.to_vec()),
+            None => std::fs::read(path),
+        }
+    }
+}
+
+fn save_index(path: &std::path::Path, entries: &VecDeque<Entry>) {
+    if let Err(e) = storage::save(path, entries) {
+        println!("audio_cache: failed to save index {:?}", e);
+    }
+}
+
+// Size bounded LRU cache of synthesized audio stored as files.
+// The index keeps the keys from least to most recently used.
+pub struct AudioCache {
+    dir: Option<PathBuf>,
+    entries: VecDeque<Entry>,
+    max_bytes: u64,
+    writer: Option<Writer>,
+}
+
+impl AudioCache {
+    pub fn load(dir: Option<PathBuf>, max_bytes: u64) -> Self {
+        let entries: VecDeque<Entry> = dir
+            .as_ref()
+            .and_then(|d| storage::load::<VecDeque<Entry>>(d.join(INDEX_FILE).as_path()))
+            .unwrap_or_default();
+
+        // Files may have been removed by hand
+        let entries: VecDeque<Entry> = entries
+            .into_iter()
+            .filter(|e| dir.as_ref().map_or(false, |d| d.join(&e.file).exists()))
+            .collect();
+
+        // Files written after the index was last saved are not known
+        if let Some(Ok(files)) = dir.as_ref().map(std::fs::read_dir) {
+            for path in files.filter_map(|f| f.ok()).map(|f| f.path()) {
+                let known = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(true, |name| !name.ends_with(".wav") || entries.iter().any(|e| e.file == name));
+                if !known {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+
+        let mut cache = AudioCache {
+            writer: dir.as_ref().map(|_| Writer::start()),
+            dir,
+            entries,
+            max_bytes,
+        };
+        cache.evict();
+        cache
+    }
+
+    pub fn size(&self) -> u64 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.iter().any(|e| e.key == key)
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<Vec<u8>> {
+        let dir = self.dir.as_ref()?;
+        let index = self.entries.iter().position(|e| e.key == key)?;
+
+        let read = match self.writer {
+            Some(ref writer) => writer.read(dir.join(&self.entries[index].file).as_path()),
+            None => return None,
+        };
+        match read {
+            Ok(data) => {
+                let entry = self.entries.remove(index).unwrap();
+                self.entries.push_back(entry);
+                Some(data)
+            }
+            Err(e) => {
+                println!("audio_cache: failed to read {:?} {:?}", key, e);
+                self.entries.remove(index);
+                self.index_changed();
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: String, data: &[u8]) {
+        let dir = match self.dir {
+            Some(ref dir) => dir.clone(),
+            None => return,
+        };
+
+        if let Some(index) = self.entries.iter().position(|e| e.key == key) {
+            self.entries.remove(index);
+        }
+
+        let file = format!("{:016x}.wav", scheduler::stable_hash(key.as_str()));
+        if let Some(ref writer) = self.writer {
+            writer.send(Op::Write(dir.join(&file), Arc::new(data.to_vec())));
+        }
+
+        // Keys sharing a file name overwrite each other
+        self.entries.retain(|e| e.file != file);
+        self.entries.push_back(Entry {
+            key,
+            file,
+            size: data.len() as u64,
+        });
+
+        self.evict();
+        self.index_changed();
+    }
+
+    pub fn remove_if<F: Fn(&str) -> bool>(&mut self, f: F) {
+        let (removed, kept): (Vec<Entry>, Vec<Entry>) =
+            self.entries.drain(..).partition(|e| f(e.key.as_str()));
+        self.entries = kept.into();
+
+        for entry in removed {
+            self.remove_file(&entry);
+        }
+        self.index_changed();
+    }
+
+    pub fn clear(&mut self) {
+        self.remove_if(|_| true);
+    }
+
+    fn evict(&mut self) {
+        while self.size() > self.max_bytes {
+            let entry = self.entries.pop_front().unwrap();
+            self.remove_file(&entry);
+        }
+    }
+
+    fn remove_file(&self, entry: &Entry) {
+        if let (Some(ref dir), Some(ref writer)) = (&self.dir, &self.writer) {
+            writer.send(Op::Remove(dir.join(&entry.file)));
+        }
+    }
+
+    fn index_changed(&self) {
+        if let (Some(ref dir), Some(ref writer)) = (&self.dir, &self.writer) {
+            writer.send(Op::Index(dir.join(INDEX_FILE), self.entries.clone()));
+        }
+    }
+}
+
+impl Drop for AudioCache {
+    // Waits until everything is on disk
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            drop(writer.tx);
+            let _ = writer.thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("audio_cache_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn ts_lru_eviction() {
+        let dir = temp_dir("lru");
+        let mut cache = AudioCache::load(Some(dir.clone()), 8);
+
+        cache.insert("a".to_string(), &[0; 4]);
+        cache.insert("b".to_string(), &[1; 4]);
+        assert_eq!(cache.get("a"), Some(vec![0; 4]));
+
+        // "b" is the least recently used
+        cache.insert("c".to_string(), &[2; 4]);
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("b"));
+        assert!(cache.contains("c"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn ts_persist_and_remove() {
+        let dir = temp_dir("persist");
+        {
+            let mut cache = AudioCache::load(Some(dir.clone()), 1024);
+            cache.insert("x/1".to_string(), &[1, 2, 3]);
+            cache.insert("y/1".to_string(), &[4, 5, 6]);
+        }
+
+        let mut cache = AudioCache::load(Some(dir.clone()), 1024);
+        assert_eq!(cache.get("x/1"), Some(vec![1, 2, 3]));
+
+        cache.remove_if(|key| key.starts_with("x/"));
+        assert_eq!(cache.get("x/1"), None);
+        assert_eq!(cache.size(), 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn ts_unknown_files_removed() {
+        let dir = temp_dir("unknown");
+        {
+            let mut cache = AudioCache::load(Some(dir.clone()), 1024);
+            cache.insert("a".to_string(), &[1]);
+            // Readable before it is written
+            assert_eq!(cache.get("a"), Some(vec![1]));
+        }
+        std::fs::write(dir.join("0000000000000000.wav"), [2]).unwrap();
+
+        let mut cache = AudioCache::load(Some(dir.clone()), 1024);
+        assert_eq!(cache.get("a"), Some(vec![1]));
+        assert!(!dir.join("0000000000000000.wav").exists());
+        assert!(dir.join(INDEX_FILE).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn ts_index_saved_while_open() {
+        let dir = temp_dir("index");
+        let mut cache = AudioCache::load(Some(dir.clone()), 1024);
+        cache.insert("a".to_string(), &[1]);
+
+        // Saved once the writer is idle, long before the cache is dropped
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let saved = || {
+            storage::load::<VecDeque<Entry>>(dir.join(INDEX_FILE).as_path())
+                .map_or(false, |entries| entries.iter().any(|e| e.key == "a"))
+        };
+        while !saved() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(saved());
+
+        drop(cache);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,92 +1,677 @@
-use rodio::Sink;
+use rodio::{Sink, Source};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::audio_sink;
+use crate::host;
+use crate::loudness;
+use crate::time_stretch;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AudioControl {
-    Tick,
     Play(Vec<u8>),
     PlayMulti(Vec<Vec<u8>>),
+    // The next speech is a priority one, announced by a tone of its own
+    Chime,
+    // Engine of the speeches that follow, by name
+    Engine(String),
+    Cues(CueSettings),
+    Loudness(loudness::LoudnessSettings),
     Volume(u32),
+    // Playback speed, pitch kept, applied to what is being played too.
+    // Separate from the speech rate the engines synthesize at.
+    Speed(f64),
     Pause,
     Resume,
     Stop,
+    // Moves playback to another output, starting over what is being played
+    Output(audio_sink::Target),
     Quit,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Separator {
+    Silence,
+    Chime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CueSettings {
+    // Played before each tweet, the chime followed by the gap
+    pub separator: Separator,
+    pub gap_ms: u64,
+    // Between the name and the text
+    pub name_gap_ms: u64,
+    // Looped under the speech
+    pub bed: Option<PathBuf>,
+    // Percent of the speech volume
+    pub bed_volume: u32,
+    // Percent of the bed volume kept while speaking
+    pub ducking: u32,
+}
+
+impl CueSettings {
+    pub fn new() -> Self {
+        CueSettings {
+            separator: Separator::Silence,
+            gap_ms: 0,
+            name_gap_ms: 0,
+            bed: None,
+            bed_volume: 30,
+            ducking: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum AudioError {
+    // The speech could not be decoded and was skipped
+    Decode(String),
+    // The output stopped playing and was opened again
+    Output(String),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AudioControlRdy {}
+pub struct AudioControlRdy {
+    // Why the last speech was not played to the end
+    pub error: Option<AudioError>,
+}
 
-pub fn start(
-    _app_handle: tauri::AppHandle,
-    mut audioctl_rx: tokio::sync::mpsc::Receiver<AudioControl>,
-    audioctl_rdy_tx: tokio::sync::mpsc::Sender<AudioControlRdy>,
+// Time allowed beyond the length of the audio before the output is considered stuck
+const STALL_MARGIN_MS: u64 = 5000;
+// For audio of unknown length
+const LENGTH_UNKNOWN_MS: u64 = 60000;
+// The bed is faded in and out over about DUCK_STEP_MS / DUCK_STEP
+const DUCK_STEP_MS: u64 = 20;
+const DUCK_STEP: f32 = 0.1;
+
+// Ends at once, running the callback when the sink gets to it
+struct Callback {
+    callback: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Iterator for Callback {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(callback) = self.callback.take() {
+            callback();
+        }
+        None
+    }
+}
+
+impl Source for Callback {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        24000
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        Some(std::time::Duration::ZERO)
+    }
+}
+
+// What the player thread waits for
+enum Event {
+    Control(AudioControl),
+    // The sink played everything appended to it
+    Finished,
+    // No more commands to come
+    Closed,
+}
+
+// Tells the player thread once the sink has played everything appended so far,
+// which passes the notice on to the scheduler.
+// Only one notice is sent per playback, whether it finished or was stopped.
+fn append_finished(
+    sink: &Sink,
+    playing: &std::sync::Arc<std::sync::atomic::AtomicBool>,
+    speaking: &std::sync::Arc<std::sync::atomic::AtomicBool>,
+    events_tx: &std::sync::mpsc::Sender<Event>,
 ) {
-    std::thread::spawn(move || {
-        let (_os, osh) = rodio::OutputStream::try_default().expect("failed to open audio device");
+    let playing = playing.clone();
+    let speaking = speaking.clone();
+    let events_tx = events_tx.clone();
+
+    sink.append(Callback {
+        callback: Some(Box::new(move || {
+            speaking.store(false, std::sync::atomic::Ordering::SeqCst);
+            if playing.swap(false, std::sync::atomic::Ordering::SeqCst) {
+                println!("audio_coordinator: playback finished");
+                // Unbounded, so the audio thread never waits. Fails only once the player is gone.
+                let _ = events_tx.send(Event::Finished);
+            }
+        })),
+    });
+}
 
-        let mut sink = Sink::try_new(&osh).expect("failed to create new sink");
+// Appends the tones and returns their length
+fn append_tones(sink: &Sink, tones: &[(f32, u64)], amplitude: f32) -> std::time::Duration {
+    for (freq, ms) in tones.iter() {
+        let tone = rodio::source::SineWave::new(*freq)
+            .take_duration(std::time::Duration::from_millis(*ms))
+            .amplify(amplitude);
+        sink.append(tone);
+    }
 
-        audioctl_rdy_tx.blocking_send(AudioControlRdy {}).unwrap();
-        let mut playing = false;
+    std::time::Duration::from_millis(tones.iter().map(|(_, ms)| ms).sum())
+}
+
+fn append_silence(sink: &Sink, ms: u64) -> std::time::Duration {
+    let length = std::time::Duration::from_millis(ms);
+    if ms > 0 {
+        sink.append(rodio::source::Zero::<f32>::new(1, 24000).take_duration(length));
+    }
 
+    length
+}
+
+// Priority tweets are announced louder and higher than the others are separated
+fn append_cue(sink: &Sink, cues: &CueSettings, priority: bool) -> std::time::Duration {
+    let tone = if priority {
+        append_tones(sink, &[(880.0, 120), (1320.0, 200)], 0.2)
+    } else if cues.separator == Separator::Chime {
+        append_tones(sink, &[(660.0, 150)], 0.1)
+    } else {
+        std::time::Duration::ZERO
+    };
+
+    tone + append_silence(sink, cues.gap_ms)
+}
+
+fn decode(data: Vec<u8>) -> Result<rodio::Decoder<std::io::Cursor<Vec<u8>>>, AudioError> {
+    rodio::Decoder::new(std::io::Cursor::new(data)).map_err(|e| AudioError::Decode(e.to_string()))
+}
+
+// Played at whatever speed is set meanwhile
+struct Stretched {
+    stretch: time_stretch::TimeStretch,
+    // Bits of an f64
+    speed: std::sync::Arc<std::sync::atomic::AtomicU64>,
+    hop: std::vec::IntoIter<f32>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Iterator for Stretched {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
         loop {
-            match audioctl_rx.blocking_recv() {
-                Some(msg) => match msg {
-                    AudioControl::Tick => {
-                        if sink.empty() && playing {
-                            println!("audio_coordinator: sink empty");
-                            let _ = audioctl_rdy_tx.try_send(AudioControlRdy {});
-                            playing = false;
-                        }
-                    }
+            if let Some(sample) = self.hop.next() {
+                return Some(sample);
+            }
+            let speed = f64::from_bits(self.speed.load(std::sync::atomic::Ordering::SeqCst));
+            self.hop = self.stretch.next_hop(speed)?.into_iter();
+        }
+    }
+}
 
-                    AudioControl::Play(data) => {
-                        println!("audio_coordinator: recv Play");
+impl Source for Stretched {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
 
-                        let source = rodio::Decoder::new(std::io::Cursor::new(data))
-                            .expect("failed to decord wav");
-                        sink.append(source);
-                        playing = true;
-                    }
+    fn channels(&self) -> u16 {
+        self.channels
+    }
 
-                    AudioControl::PlayMulti(audio_vec) => {
-                        println!("audio_coordinator: recv PlayVec");
-                        for data in audio_vec {
-                            let source = rodio::Decoder::new(std::io::Cursor::new(data))
-                                .expect("failed to decord wav");
-                            sink.append(source);
-                        }
-                        playing = true;
-                    }
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
 
-                    AudioControl::Volume(n) => {
-                        println!("audio_coordinator: recv Volume {:?}", n);
-                        sink.set_volume(n as f32 / 100f32);
-                    }
+    // Depends on the speed to come
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
 
-                    AudioControl::Pause => {
-                        println!("audio_coordinator: recv Pause");
-                        sink.pause();
-                    }
+// Decoded, brought to the target loudness and stretched to the playback speed.
+// Returns the length at normal speed.
+fn prepare(
+    data: Vec<u8>,
+    settings: &loudness::LoudnessSettings,
+    engine: &str,
+    speed: &std::sync::Arc<std::sync::atomic::AtomicU64>,
+) -> Result<(rodio::source::Amplify<Stretched>, std::time::Duration), AudioError> {
+    let decoder = decode(data)?;
+    let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+    let samples: Vec<f32> = decoder.convert_samples().collect();
+    let gain = settings.gain(&samples, channels, sample_rate, engine);
 
-                    AudioControl::Resume => {
-                        println!("audio_coordinator: recv Resume");
-                        sink.play();
-                    }
+    let length = if channels > 0 && sample_rate > 0 {
+        std::time::Duration::from_secs_f64(samples.len() as f64 / channels as f64 / sample_rate as f64)
+    } else {
+        std::time::Duration::from_millis(LENGTH_UNKNOWN_MS)
+    };
+    let source = Stretched {
+        stretch: time_stretch::TimeStretch::new(samples, channels, sample_rate),
+        speed: speed.clone(),
+        hop: vec![].into_iter(),
+        channels,
+        sample_rate,
+    };
 
-                    AudioControl::Stop => {
-                        println!("audio_coordinator: recv Stop");
-                        let vol = sink.volume();
-                        sink = Sink::try_new(&osh).expect("failed to create new sink");
-                        sink.set_volume(vol);
-                    }
+    Ok((source.amplify(gain), length))
+}
+
+// Looped, faded down while speaking
+fn bed_source(
+    data: Vec<u8>,
+    ducking: f32,
+    speaking: std::sync::Arc<std::sync::atomic::AtomicBool>,
+) -> Result<impl Source<Item = i16> + Send + 'static, AudioError> {
+    let mut level = 1.0f32;
+
+    Ok(decode(data)?
+        .repeat_infinite()
+        .amplify(level)
+        .periodic_access(std::time::Duration::from_millis(DUCK_STEP_MS), move |src| {
+            let target = if speaking.load(std::sync::atomic::Ordering::SeqCst) {
+                ducking
+            } else {
+                1.0
+            };
+            level = if level < target {
+                (level + DUCK_STEP).min(target)
+            } else {
+                (level - DUCK_STEP).max(target)
+            };
+            src.set_factor(level);
+        }))
+}
+
+struct Player {
+    host: host::Host,
+    target: audio_sink::Target,
+    output: Box<dyn audio_sink::AudioSink>,
+    sink: Sink,
+    // Mixed with the speech on the same output
+    bed: Option<Sink>,
+    cues: CueSettings,
+    loudness: loudness::LoudnessSettings,
+    engine: String,
+    volume: f32,
+    // Bits of an f64, shared with the speech being played
+    speed: std::sync::Arc<std::sync::atomic::AtomicU64>,
+    // Belongs to the sink, so that callbacks of a dropped one do nothing
+    playing: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    // Whether the bed is ducked
+    speaking: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    priority: bool,
+    // What is being played, to start over on another output
+    current: Vec<Vec<u8>>,
+    // When the playback should have finished, and since when it is paused
+    deadline: Option<std::time::Instant>,
+    paused_at: Option<std::time::Instant>,
+    audioctl_rdy_tx: tokio::sync::mpsc::Sender<AudioControlRdy>,
+    // For the callbacks of the sink
+    events_tx: std::sync::mpsc::Sender<Event>,
+}
+
+// Never fails: a target that cannot be played on gives way to the null sink,
+// and as a last resort the queue is played nowhere at all.
+fn open_queue(target: &audio_sink::Target) -> (Box<dyn audio_sink::AudioSink>, Sink) {
+    for target in [target, &audio_sink::Target::Null] {
+        let mut output = audio_sink::open(target);
+        match output.queue() {
+            Ok(sink) => return (output, sink),
+            Err(e) => println!("audio_coordinator: failed to create new sink {:?}", e),
+        }
+    }
+
+    (audio_sink::open(&audio_sink::Target::Null), Sink::new_idle().0)
+}
+
+impl Player {
+    fn new(
+        host: host::Host,
+        target: audio_sink::Target,
+        audioctl_rdy_tx: tokio::sync::mpsc::Sender<AudioControlRdy>,
+        events_tx: std::sync::mpsc::Sender<Event>,
+    ) -> Self {
+        let (output, sink) = open_queue(&target);
+
+        Player {
+            host,
+            target,
+            output,
+            sink,
+            bed: None,
+            cues: CueSettings::new(),
+            loudness: loudness::LoudnessSettings::new(),
+            engine: "".to_string(),
+            volume: 1.0,
+            speed: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(1f64.to_bits())),
+            playing: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            speaking: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            priority: false,
+            current: vec![],
+            deadline: None,
+            paused_at: None,
+            audioctl_rdy_tx,
+            events_tx,
+        }
+    }
+
+    fn is_playing(&self) -> bool {
+        self.playing.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn speed(&self) -> f64 {
+        f64::from_bits(self.speed.load(std::sync::atomic::Ordering::SeqCst))
+    }
+
+    // What is left to play takes longer or shorter, the stall margin aside
+    fn set_speed(&mut self, speed: f64, now: std::time::Instant) {
+        let speed = speed.clamp(time_stretch::SPEED_MIN, time_stretch::SPEED_MAX);
+        let ratio = self.speed() / speed;
+        self.speed.store(speed.to_bits(), std::sync::atomic::Ordering::SeqCst);
+
+        if let Some(deadline) = self.deadline {
+            let margin = std::time::Duration::from_millis(STALL_MARGIN_MS);
+            let now = self.paused_at.unwrap_or(now);
+            let left = deadline.saturating_duration_since(now + margin);
+            self.deadline = Some(now + left.mul_f64(ratio) + margin);
+        }
+    }
+
+    fn set_output(&mut self, output: Box<dyn audio_sink::AudioSink>) {
+        // Streams of a device are closed with it, so the bed goes first
+        self.bed = None;
+        self.output = output;
+        self.start_bed();
+    }
+
+    fn start_bed(&mut self) {
+        self.bed = None;
+
+        let path = match self.cues.bed.as_ref() {
+            Some(path) => path,
+            None => return,
+        };
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) => {
+                println!("audio_coordinator: failed to read {:?} {:?}", path, e);
+                return;
+            }
+        };
+        let source = match bed_source(data, self.cues.ducking.min(100) as f32 / 100f32, self.speaking.clone()) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("audio_coordinator: failed to decode {:?} {:?}", path, e);
+                return;
+            }
+        };
+
+        match self.output.queue() {
+            Ok(bed) => {
+                bed.set_volume(self.volume * self.cues.bed_volume as f32 / 100f32);
+                if self.paused_at.is_some() {
+                    bed.pause();
+                }
+                bed.append(source);
+                self.bed = Some(bed);
+            }
+            Err(e) => println!("audio_coordinator: failed to create new sink {:?}", e),
+        }
+    }
+
+    // Drops whatever is queued, keeping the volume and whether paused.
+    // Returns whether something was being played.
+    fn renew_sink(&mut self) -> bool {
+        let sink = match self.output.queue() {
+            Ok(sink) => sink,
+            Err(e) => {
+                println!("audio_coordinator: failed to create new sink {:?}", e);
+                let (output, sink) = open_queue(&self.target);
+                self.set_output(output);
+                sink
+            }
+        };
+        sink.set_volume(self.volume);
+        if self.paused_at.is_some() {
+            sink.pause();
+        }
+        self.sink = sink;
+        self.deadline = None;
+        self.priority = false;
+        self.speaking.store(false, std::sync::atomic::Ordering::SeqCst);
+
+        let playing = self.playing.swap(false, std::sync::atomic::Ordering::SeqCst);
+        self.playing = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        playing
+    }
+
+    // Waits for the scheduler to take the notice rather than lose it.
+    // The scheduler takes notices while sending commands, which never wait on this thread.
+    fn report(&self, error: Option<AudioError>) {
+        if self.audioctl_rdy_tx.blocking_send(AudioControlRdy { error }).is_err() {
+            println!("audio_coordinator: audioctl_rdy_rx closed");
+        }
+    }
+
+    // Reports the speech as skipped
+    fn fail(&mut self, error: AudioError) {
+        println!("audio_coordinator: {:?}", error);
+        self.host.emit("tauri://frontend/audio-failed", "音声の再生に失敗しました");
+
+        self.playing.store(false, std::sync::atomic::Ordering::SeqCst);
+        self.speaking.store(false, std::sync::atomic::Ordering::SeqCst);
+        self.deadline = None;
+        self.report(Some(error));
+    }
+
+    // The cue, then the parts with gaps between them.
+    // Nothing is played unless every part decodes.
+    fn play(&mut self, audio_vec: Vec<Vec<u8>>, now: std::time::Instant) {
+        let priority = std::mem::take(&mut self.priority);
+        let sources = audio_vec
+            .iter()
+            .cloned()
+            .map(|data| prepare(data, &self.loudness, self.engine.as_str(), &self.speed))
+            .collect::<Result<Vec<_>, _>>();
+        let sources = match sources {
+            Ok(sources) => sources,
+            Err(e) => {
+                self.current = vec![];
+                self.fail(e);
+                return;
+            }
+        };
+        self.host.emit("tauri://frontend/audio-failed", "");
 
-                    AudioControl::Quit => {
-                        println!("audio_coordinator: recv Quit");
+        self.playing.store(true, std::sync::atomic::Ordering::SeqCst);
+        self.speaking.store(true, std::sync::atomic::Ordering::SeqCst);
+
+        let mut length = append_cue(&self.sink, &self.cues, priority);
+        for (i, (source, source_length)) in sources.into_iter().enumerate() {
+            if i > 0 {
+                length += append_silence(&self.sink, self.cues.name_gap_ms);
+            }
+            length += source_length.div_f64(self.speed());
+            self.sink.append(source);
+        }
+        append_finished(&self.sink, &self.playing, &self.speaking, &self.events_tx);
+        self.current = audio_vec;
+
+        let start = self.paused_at.unwrap_or(now);
+        self.deadline = Some(start + length + std::time::Duration::from_millis(STALL_MARGIN_MS));
+    }
+
+    // When the playback should have finished by, unless paused
+    fn watched_until(&self) -> Option<std::time::Instant> {
+        if !self.is_playing() || self.paused_at.is_some() {
+            return None;
+        }
+
+        self.deadline
+    }
+
+    // A playback running past its length means the output stopped working,
+    // for instance a device that was unplugged. The output is opened again.
+    fn watch(&mut self, now: std::time::Instant) {
+        if self.watched_until().map_or(false, |deadline| now >= deadline) {
+            let name = self.output.name();
+            self.set_output(audio_sink::open(&self.target));
+            self.renew_sink();
+            self.fail(AudioError::Output(format!("{} stopped playing", name)));
+        }
+    }
+
+    // Returns false once asked to quit
+    fn handle(&mut self, msg: AudioControl, now: std::time::Instant) -> bool {
+        match msg {
+            AudioControl::Play(data) => {
+                println!("audio_coordinator: recv Play");
+                self.play(vec![data], now);
+            }
+
+            AudioControl::PlayMulti(audio_vec) => {
+                println!("audio_coordinator: recv PlayVec");
+                self.play(audio_vec, now);
+            }
+
+            AudioControl::Chime => {
+                println!("audio_coordinator: recv Chime");
+                self.priority = true;
+            }
+
+            AudioControl::Engine(engine) => {
+                println!("audio_coordinator: recv Engine {:?}", engine);
+                self.engine = engine;
+            }
+
+            AudioControl::Loudness(settings) => {
+                println!("audio_coordinator: recv Loudness {:?}", settings);
+                self.loudness = settings;
+            }
+
+            AudioControl::Cues(cues) => {
+                println!("audio_coordinator: recv Cues {:?}", cues);
+                let bed_changed = (&cues.bed, cues.bed_volume, cues.ducking)
+                    != (&self.cues.bed, self.cues.bed_volume, self.cues.ducking);
+                self.cues = cues;
+                if bed_changed {
+                    self.start_bed();
+                }
+            }
+
+            AudioControl::Volume(n) => {
+                println!("audio_coordinator: recv Volume {:?}", n);
+                self.volume = n as f32 / 100f32;
+                self.sink.set_volume(self.volume);
+                if let Some(bed) = self.bed.as_ref() {
+                    bed.set_volume(self.volume * self.cues.bed_volume as f32 / 100f32);
+                }
+            }
+
+            AudioControl::Speed(speed) => {
+                println!("audio_coordinator: recv Speed {:?}", speed);
+                self.set_speed(speed, now);
+            }
+
+            AudioControl::Pause => {
+                println!("audio_coordinator: recv Pause");
+                self.sink.pause();
+                if let Some(bed) = self.bed.as_ref() {
+                    bed.pause();
+                }
+                self.paused_at.get_or_insert(now);
+            }
+
+            AudioControl::Resume => {
+                println!("audio_coordinator: recv Resume");
+                self.sink.play();
+                if let Some(bed) = self.bed.as_ref() {
+                    bed.play();
+                }
+                if let (Some(deadline), Some(paused_at)) = (self.deadline, self.paused_at.take()) {
+                    self.deadline = Some(deadline + now.saturating_duration_since(paused_at));
+                }
+            }
+
+            AudioControl::Stop => {
+                println!("audio_coordinator: recv Stop");
+                // The callback of the dropped sink never runs
+                if self.renew_sink() {
+                    self.report(None);
+                }
+            }
+
+            AudioControl::Output(target) => {
+                println!("audio_coordinator: recv Output {:?}", target);
+                self.set_output(audio_sink::open(&target));
+                self.target = target;
+                if self.renew_sink() {
+                    let current = std::mem::take(&mut self.current);
+                    self.play(current, now);
+                }
+            }
+
+            AudioControl::Quit => {
+                println!("audio_coordinator: recv Quit");
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+pub fn start(
+    host: host::Host,
+    target: audio_sink::Target,
+    mut audioctl_rx: tokio::sync::mpsc::Receiver<AudioControl>,
+    audioctl_rdy_tx: tokio::sync::mpsc::Sender<AudioControlRdy>,
+) {
+    let (events_tx, events_rx) = std::sync::mpsc::channel::<Event>();
+
+    // Commands are passed on as they come, so that they wait along with the end of the playback
+    let control_tx = events_tx.clone();
+    std::thread::spawn(move || {
+        while let Some(msg) = audioctl_rx.blocking_recv() {
+            if control_tx.send(Event::Control(msg)).is_err() {
+                return;
+            }
+        }
+        let _ = control_tx.send(Event::Closed);
+    });
+
+    std::thread::spawn(move || {
+        let mut player = Player::new(host, target, audioctl_rdy_tx, events_tx);
+
+        player.report(None);
+
+        loop {
+            // Woken up by the time the playback should have finished, to notice a stuck output
+            let event = match player.watched_until() {
+                Some(deadline) => {
+                    events_rx.recv_timeout(deadline.saturating_duration_since(std::time::Instant::now()))
+                }
+                None => events_rx
+                    .recv()
+                    .map_err(|_| std::sync::mpsc::RecvTimeoutError::Disconnected),
+            };
+
+            match event {
+                Ok(Event::Control(msg)) => {
+                    if !player.handle(msg, std::time::Instant::now()) {
                         break;
                     }
-                },
-                None => {
+                }
+                Ok(Event::Finished) => player.report(None),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => player.watch(std::time::Instant::now()),
+                Ok(Event::Closed) | Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
                     println!("audio_coordinator: audioctl_tx closed");
                     return ();
                 }
@@ -96,3 +681,175 @@ pub fn start(
         println!("sound_coordinator: thread exit");
     });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wav;
+
+    fn voice(ms: usize) -> Vec<u8> {
+        wav::Wav {
+            channels: 1,
+            sample_rate: 24000,
+            samples: vec![1000; 24 * ms],
+        }
+        .to_bytes()
+    }
+
+    fn player() -> (
+        Player,
+        tokio::sync::mpsc::Receiver<AudioControlRdy>,
+        std::sync::mpsc::Receiver<Event>,
+    ) {
+        let (rdy_tx, rdy_rx) = tokio::sync::mpsc::channel::<AudioControlRdy>(1);
+        let (events_tx, events_rx) = std::sync::mpsc::channel::<Event>();
+        let host = host::Host::headless(std::env::temp_dir().join("twradio_player_test"));
+
+        (Player::new(host, audio_sink::Target::Null, rdy_tx, events_tx), rdy_rx, events_rx)
+    }
+
+    // Waits for the null output to play the queue out and passes the notice on as the thread does
+    fn play_out(
+        player: &Player,
+        events_rx: &std::sync::mpsc::Receiver<Event>,
+        rdy_rx: &mut tokio::sync::mpsc::Receiver<AudioControlRdy>,
+    ) -> Option<AudioControlRdy> {
+        match events_rx.recv_timeout(std::time::Duration::from_secs(10)) {
+            Ok(Event::Finished) => player.report(None),
+            _ => return None,
+        }
+        rdy_rx.try_recv().ok()
+    }
+
+    // Seconds from the start of the playback to its end, the stall margin aside
+    fn length(player: &Player, start: std::time::Instant) -> f64 {
+        (player.deadline.unwrap() - start - std::time::Duration::from_millis(STALL_MARGIN_MS)).as_secs_f64()
+    }
+
+    #[test]
+    fn ts_player_without_device() {
+        let (mut player, mut rdy_rx, events_rx) = player();
+        let start = std::time::Instant::now();
+
+        player.handle(AudioControl::Chime, start);
+        player.handle(AudioControl::PlayMulti(vec![voice(50), voice(50)]), start);
+        assert!(player.is_playing());
+        assert!(play_out(&player, &events_rx, &mut rdy_rx).unwrap().error.is_none());
+        assert!(!player.is_playing());
+
+        // Stopped playback is reported once
+        player.handle(AudioControl::Play(voice(1000)), start);
+        player.handle(AudioControl::Stop, start);
+        assert!(rdy_rx.try_recv().unwrap().error.is_none());
+        player.handle(AudioControl::Stop, start);
+        assert!(rdy_rx.try_recv().is_err());
+
+        // Switching output starts over, still reporting once
+        player.handle(AudioControl::Play(voice(200)), start);
+        player.handle(AudioControl::Output(audio_sink::Target::Null), start);
+        assert!(rdy_rx.try_recv().is_err());
+        assert!(play_out(&player, &events_rx, &mut rdy_rx).is_some());
+        assert!(events_rx.try_recv().is_err());
+
+        assert!(!player.handle(AudioControl::Quit, start));
+    }
+
+    #[test]
+    fn ts_player_cues() {
+        let (mut player, mut rdy_rx, events_rx) = player();
+        let bed = std::env::temp_dir().join(format!("twradio_bed_{}.wav", std::process::id()));
+        std::fs::write(&bed, voice(100)).unwrap();
+        let start = std::time::Instant::now();
+
+        player.handle(
+            AudioControl::Cues(CueSettings {
+                separator: Separator::Chime,
+                gap_ms: 300,
+                name_gap_ms: 100,
+                bed: Some(bed.clone()),
+                bed_volume: 50,
+                ducking: 20,
+            }),
+            start,
+        );
+        assert!(player.bed.is_some());
+
+        // Chime, gap, name, gap and text
+        player.handle(AudioControl::PlayMulti(vec![voice(50), voice(50)]), start);
+        assert!((length(&player, start) - 0.65).abs() < 1e-6);
+
+        assert!(player.speaking.load(std::sync::atomic::Ordering::SeqCst));
+        assert!(play_out(&player, &events_rx, &mut rdy_rx).is_some());
+        assert!(!player.speaking.load(std::sync::atomic::Ordering::SeqCst));
+
+        // The bed goes with the output
+        player.handle(AudioControl::Output(audio_sink::Target::Null), start);
+        assert!(player.bed.is_some());
+        player.handle(AudioControl::Cues(CueSettings::new()), start);
+        assert!(player.bed.is_none());
+
+        let _ = std::fs::remove_file(&bed);
+    }
+
+    #[test]
+    fn ts_player_speed() {
+        let (mut player, mut rdy_rx, events_rx) = player();
+        let start = std::time::Instant::now();
+
+        player.handle(AudioControl::Speed(2.0), start);
+        player.handle(AudioControl::Play(voice(1000)), start);
+        assert!((length(&player, start) - 0.5).abs() < 1e-6);
+        assert!(play_out(&player, &events_rx, &mut rdy_rx).is_some());
+
+        // Changed while playing, the length left changes along: 800 ms at twice the speed
+        player.handle(AudioControl::Speed(1.0), start);
+        player.handle(AudioControl::Play(voice(1000)), start);
+        player.handle(AudioControl::Speed(2.0), start + std::time::Duration::from_millis(200));
+        assert!((length(&player, start) - 0.6).abs() < 1e-6);
+
+        // Time spent paused is added
+        player.handle(AudioControl::Pause, start + std::time::Duration::from_millis(300));
+        player.handle(AudioControl::Resume, start + std::time::Duration::from_millis(600));
+        assert!((length(&player, start) - 0.9).abs() < 1e-6);
+        assert!(play_out(&player, &events_rx, &mut rdy_rx).is_some());
+
+        // Clamped
+        player.handle(AudioControl::Speed(100.0), start);
+        assert_eq!(player.speed(), time_stretch::SPEED_MAX);
+    }
+
+    #[test]
+    fn ts_player_errors() {
+        let (mut player, mut rdy_rx, events_rx) = player();
+        let start = std::time::Instant::now();
+
+        // Broken speech is skipped as a whole
+        player.handle(AudioControl::PlayMulti(vec![voice(50), b"<html>".to_vec()]), start);
+        assert!(!player.is_playing());
+        assert!(matches!(rdy_rx.try_recv().unwrap().error, Some(AudioError::Decode(_))));
+
+        // Output stuck past the length of the speech
+        player.handle(AudioControl::Play(voice(1000)), start);
+        player.watch(start);
+        assert!(player.is_playing());
+
+        let late = start + std::time::Duration::from_millis(STALL_MARGIN_MS + 1000);
+        assert_eq!(player.watched_until(), Some(late));
+        player.watch(late);
+        assert!(!player.is_playing());
+        assert!(matches!(rdy_rx.try_recv().unwrap().error, Some(AudioError::Output(_))));
+
+        // Reported once, the dropped queue stays silent
+        assert_eq!(player.watched_until(), None);
+        player.handle(AudioControl::Play(voice(50)), start);
+        assert!(play_out(&player, &events_rx, &mut rdy_rx).is_some());
+        assert!(events_rx.try_recv().is_err());
+
+        // Paused playback is not stuck
+        player.handle(AudioControl::Pause, start);
+        player.handle(AudioControl::Play(voice(50)), start);
+        assert_eq!(player.watched_until(), None);
+        player.watch(late);
+        assert!(player.is_playing());
+    }
+}
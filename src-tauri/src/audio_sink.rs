@@ -0,0 +1,244 @@
+use rodio::cpal::traits::HostTrait;
+use rodio::{DeviceTrait, Sink};
+use serde::{Deserialize, Serialize};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::wav;
+
+// Format the null and file sinks play in, that of the voice engines
+const CHANNELS: u16 = 1;
+const SAMPLE_RATE: u32 = 24000;
+// They pull the queue in slices of this length
+const SLICE_MS: u64 = 20;
+
+// Where the audio goes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Target {
+    // The default device of the system
+    Default,
+    // A device by name, as listed by devices()
+    Device(String),
+    // Played to nowhere in real time
+    Null,
+    // Played in real time into a WAV file, silence left out
+    File(PathBuf),
+}
+
+#[derive(Debug)]
+pub enum SinkError {
+    NotFound(String),
+    Unknown(String),
+}
+
+// Names of the output devices
+pub fn devices() -> Vec<String> {
+    match rodio::cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+        Err(e) => {
+            println!("audio_sink: failed to list devices {:?}", e);
+            vec![]
+        }
+    }
+}
+
+pub trait AudioSink {
+    fn name(&self) -> String;
+
+    // A new queue mixed into the output, played until dropped
+    fn queue(&mut self) -> Result<Sink, SinkError>;
+}
+
+struct DeviceSink {
+    name: String,
+    // Playback ends when the stream is dropped
+    _stream: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+}
+
+impl DeviceSink {
+    fn open(name: Option<&str>) -> Result<Self, SinkError> {
+        let host = rodio::cpal::default_host();
+        let device = match name {
+            Some(name) => host
+                .output_devices()
+                .map_err(|e| SinkError::Unknown(e.to_string()))?
+                .find(|d| d.name().map_or(false, |n| n == name)),
+            None => host.default_output_device(),
+        }
+        .ok_or_else(|| SinkError::NotFound(name.unwrap_or("default").to_string()))?;
+
+        let (stream, handle) = rodio::OutputStream::try_from_device(&device)
+            .map_err(|e| SinkError::Unknown(e.to_string()))?;
+
+        Ok(DeviceSink {
+            name: device.name().unwrap_or_default(),
+            _stream: stream,
+            handle,
+        })
+    }
+}
+
+impl AudioSink for DeviceSink {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn queue(&mut self) -> Result<Sink, SinkError> {
+        Sink::try_new(&self.handle).map_err(|e| SinkError::Unknown(e.to_string()))
+    }
+}
+
+// WAV file growing as it is written, header kept valid
+struct WavWriter {
+    file: std::fs::File,
+    data_len: u32,
+}
+
+impl WavWriter {
+    fn create(path: &Path) -> std::io::Result<Self> {
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(&wav::header(CHANNELS, SAMPLE_RATE, 0))?;
+
+        Ok(WavWriter { file, data_len: 0 })
+    }
+
+    fn write(&mut self, samples: &[f32]) -> std::io::Result<()> {
+        let data: Vec<u8> = samples
+            .iter()
+            .flat_map(|s| ((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+            .collect();
+
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&data)?;
+        self.data_len = self.data_len.saturating_add(data.len() as u32);
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&wav::header(CHANNELS, SAMPLE_RATE, self.data_len))
+    }
+}
+
+// Queues not attached to a device, mixed and pulled by a thread of its own
+struct IdleSink {
+    name: String,
+    mixer: std::sync::Arc<rodio::dynamic_mixer::DynamicMixerController<f32>>,
+    // The thread ends once this is dropped
+    _alive_tx: std::sync::mpsc::Sender<()>,
+}
+
+impl IdleSink {
+    fn start(name: String, mut writer: Option<WavWriter>) -> Self {
+        let (mixer, mut output) = rodio::dynamic_mixer::mixer::<f32>(CHANNELS, SAMPLE_RATE);
+        let (alive_tx, alive_rx) = std::sync::mpsc::channel::<()>();
+
+        std::thread::spawn(move || {
+            let slice_len = (SAMPLE_RATE as u64 * SLICE_MS / 1000) as usize * CHANNELS as usize;
+            let mut deadline = std::time::Instant::now();
+
+            while let Err(std::sync::mpsc::TryRecvError::Empty) = alive_rx.try_recv() {
+                // The mixer has nothing to give while no queue is open
+                let slice: Vec<f32> = (0..slice_len).map(|_| output.next().unwrap_or(0.0)).collect();
+
+                if let Some(w) = writer.as_mut() {
+                    if slice.iter().any(|s| *s != 0.0) {
+                        if let Err(e) = w.write(&slice) {
+                            println!("audio_sink: failed to write {:?}", e);
+                            writer = None;
+                        }
+                    }
+                }
+
+                deadline += std::time::Duration::from_millis(SLICE_MS);
+                std::thread::sleep(deadline.saturating_duration_since(std::time::Instant::now()));
+            }
+        });
+
+        IdleSink {
+            name,
+            mixer,
+            _alive_tx: alive_tx,
+        }
+    }
+
+    fn null() -> Self {
+        IdleSink::start("null".to_string(), None)
+    }
+
+    fn file(path: &Path) -> Result<Self, SinkError> {
+        let writer = WavWriter::create(path).map_err(|e| SinkError::Unknown(e.to_string()))?;
+
+        Ok(IdleSink::start(path.display().to_string(), Some(writer)))
+    }
+}
+
+impl AudioSink for IdleSink {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn queue(&mut self) -> Result<Sink, SinkError> {
+        let (sink, queue) = Sink::new_idle();
+        self.mixer.add(queue);
+
+        Ok(sink)
+    }
+}
+
+// Falls back to the default device when the one asked for is missing,
+// and to the null sink when there is no device at all.
+pub fn open(target: &Target) -> Box<dyn AudioSink> {
+    let opened: Result<Box<dyn AudioSink>, SinkError> = match target {
+        Target::Default => DeviceSink::open(None).map(|s| Box::new(s) as Box<dyn AudioSink>),
+
+        Target::Device(name) => DeviceSink::open(Some(name))
+            .or_else(|e| {
+                println!("audio_sink: failed to open {:?} {:?}", name, e);
+                DeviceSink::open(None)
+            })
+            .map(|s| Box::new(s) as Box<dyn AudioSink>),
+
+        Target::Null => Ok(Box::new(IdleSink::null())),
+
+        Target::File(path) => IdleSink::file(path).map(|s| Box::new(s) as Box<dyn AudioSink>),
+    };
+
+    match opened {
+        Ok(sink) => {
+            println!("audio_sink: playing on {:?}", sink.name());
+            sink
+        }
+        Err(e) => {
+            println!("audio_sink: failed to open {:?} {:?}, playing to nowhere", target, e);
+            Box::new(IdleSink::null())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ts_file_sink() {
+        let path = std::env::temp_dir().join(format!("twradio_sink_{}.wav", std::process::id()));
+        let mut sink = open(&Target::File(path.clone()));
+        assert_eq!(sink.name(), path.display().to_string());
+
+        let queue = sink.queue().unwrap();
+        let voice = wav::Wav {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            samples: vec![1000; SAMPLE_RATE as usize / 10],
+        };
+        queue.append(rodio::Decoder::new(std::io::Cursor::new(voice.to_bytes())).unwrap());
+        queue.sleep_until_end();
+        std::thread::sleep(std::time::Duration::from_millis(SLICE_MS * 2));
+
+        // Slices partly silent are written whole
+        let written = wav::Wav::parse(&std::fs::read(&path).unwrap()).unwrap();
+        assert!(written.duration() >= voice.duration());
+        assert!(written.duration() <= voice.duration() + 2.0 * SLICE_MS as f64 / 1000.0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::storage;
+use crate::voicegen_observer;
+
+pub const FILE_NAME: &str = "author_rules.json";
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuthorRule {
+    pub author_id: String,
+    #[serde(default)]
+    pub speaker: Option<voicegen_observer::Speaker>,
+    #[serde(default)]
+    pub speech_rate: Option<f64>,
+    // Spoken instead of the display name
+    #[serde(default)]
+    pub nickname: Option<String>,
+    #[serde(default = "default_true")]
+    pub read_name: bool,
+}
+
+// Ordered by author id, so that the file does not change from run to run
+pub type RuleMap = BTreeMap<String, AuthorRule>;
+
+pub struct AuthorRules {
+    path: Option<PathBuf>,
+    rules: RuleMap,
+}
+
+impl AuthorRules {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let rules: Vec<AuthorRule> = path
+            .as_ref()
+            .and_then(|p| storage::load(p.as_path()))
+            .unwrap_or_default();
+
+        AuthorRules {
+            path,
+            rules: rules
+                .into_iter()
+                .map(|r| (r.author_id.clone(), r))
+                .collect(),
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        match self.path {
+            Some(ref path) => {
+                let rules: Vec<&AuthorRule> = self.rules.values().collect();
+                storage::save(path.as_path(), &rules)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn rules(&self) -> RuleMap {
+        self.rules.clone()
+    }
+
+    pub fn set(&mut self, rule: AuthorRule) -> std::io::Result<()> {
+        self.rules.insert(rule.author_id.clone(), rule);
+
+        self.save()
+    }
+
+    pub fn remove(&mut self, author_id: &str) -> std::io::Result<()> {
+        self.rules.remove(author_id);
+
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rule(author_id: &str, nickname: &str) -> AuthorRule {
+        AuthorRule {
+            author_id: author_id.to_string(),
+            speaker: None,
+            speech_rate: None,
+            nickname: Some(nickname.to_string()),
+            read_name: true,
+        }
+    }
+
+    #[test]
+    fn ts_save_load() {
+        let path = std::env::temp_dir()
+            .join(format!("twradio_author_rules_{}", std::process::id()))
+            .join(FILE_NAME);
+
+        let mut rules = AuthorRules::load(Some(path.clone()));
+        rules.set(rule("300", "c")).unwrap();
+        rules.set(rule("100", "a")).unwrap();
+        rules.set(rule("200", "b")).unwrap();
+        rules.remove("300").unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+
+        let loaded = AuthorRules::load(Some(path.clone()));
+        assert_eq!(loaded.rules(), rules.rules());
+        assert_eq!(loaded.rules().keys().collect::<Vec<_>>(), vec!["100", "200"]);
+
+        // Saved again as it was
+        rules.set(rule("100", "a")).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), json);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,436 @@
+// Reads timelines aloud without the webview, controlled from the terminal.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use app::{
+    audio_player,
+    audio_sink,
+    author_rules,
+    display_bridge,
+    host,
+    loudness,
+    mute_rules,
+    priority_rules,
+    recorder,
+    scheduler,
+    storage,
+    time_stretch,
+    translator,
+    twitter_agent,
+    twitter_authorizator,
+    user_input,
+    voicegen_agent,
+    voicegen_observer,
+};
+
+const QUEUE_LENGTH: usize = 256;
+// Same directory as the app, so the dictionary, rules and caches are shared
+const APP_IDENTIFIER: &str = "twradio.tapoh22334.jp";
+const CONFIG_FILE_NAME: &str = "headless.json";
+const VOLUME_STEP: u32 = 10;
+const SPEED_STEP: f64 = 0.1;
+
+const USAGE: &str = "usage: headless [--config FILE] [--data-dir DIR] [--search QUERY] \
+                     [--engine ADDR] [--speaker ID] [--rate RATE] [--speed SPEED] [--volume 0-100] \
+                     [--output default|null|file:PATH|DEVICE]";
+
+const KEYS: &str = "keys (followed by Enter): \
+                    p pause/resume, n next, b previous, r repeat, s N skip N tweets, c clear queue, \
+                    + volume up, - volume down, > faster, < slower, q quit";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Config {
+    data_dir: Option<PathBuf>,
+    // The home timeline is read when empty
+    search: String,
+    engine: std::net::SocketAddr,
+    speaker: u64,
+    // Synthesis rate, speech already synthesized is discarded when changed
+    speech_rate: f64,
+    // Playback speed, changed without synthesizing again
+    playback_speed: f64,
+    volume: u32,
+    output: audio_sink::Target,
+    cues: audio_player::CueSettings,
+    loudness: loudness::LoudnessSettings,
+    record: recorder::RecordSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_dir: None,
+            search: "".to_string(),
+            engine: std::net::SocketAddr::from(([127, 0, 0, 1], 50031)),
+            speaker: 0,
+            speech_rate: 1.0,
+            playback_speed: 1.0,
+            volume: 80,
+            output: audio_sink::Target::Default,
+            cues: audio_player::CueSettings::new(),
+            loudness: loudness::LoudnessSettings::new(),
+            record: recorder::RecordSettings::new(),
+        }
+    }
+}
+
+impl Config {
+    fn apply(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        let invalid = |e: &dyn std::fmt::Display| format!("{} {:?}: {}", flag, value, e);
+
+        match flag {
+            "--config" => {}
+            "--data-dir" => self.data_dir = Some(PathBuf::from(value)),
+            "--search" => self.search = value.to_string(),
+            "--engine" => self.engine = value.parse().map_err(|e| invalid(&e))?,
+            "--speaker" => self.speaker = value.parse().map_err(|e| invalid(&e))?,
+            "--rate" => self.speech_rate = value.parse().map_err(|e| invalid(&e))?,
+            "--speed" => self.playback_speed = value.parse().map_err(|e| invalid(&e))?,
+            "--volume" => self.volume = value.parse::<u32>().map_err(|e| invalid(&e))?.min(100),
+            "--output" => self.output = parse_output(value),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+
+        Ok(())
+    }
+
+    fn timeline(&self) -> twitter_agent::Timeline {
+        if self.search.is_empty() {
+            twitter_agent::Timeline::User
+        } else {
+            twitter_agent::Timeline::Search {
+                query: self.search.clone(),
+            }
+        }
+    }
+}
+
+fn parse_output(value: &str) -> audio_sink::Target {
+    match value {
+        "default" => audio_sink::Target::Default,
+        "null" => audio_sink::Target::Null,
+        _ => match value.strip_prefix("file:") {
+            Some(path) => audio_sink::Target::File(PathBuf::from(path)),
+            None => audio_sink::Target::Device(value.to_string()),
+        },
+    }
+}
+
+fn parse_flags(args: &[String]) -> Result<Vec<(String, String)>, String> {
+    let mut flags = vec![];
+    let mut args = args.iter();
+
+    while let Some(flag) = args.next() {
+        if !flag.starts_with("--") {
+            return Err(format!("unexpected argument {}", flag));
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value of {}", flag))?;
+        flags.push((flag.clone(), value.clone()));
+    }
+
+    Ok(flags)
+}
+
+// Flags override the config file, which is looked up in the data directory by default
+fn load_config(args: &[String]) -> Result<Config, String> {
+    let flags = parse_flags(args)?;
+    let flag = |name: &str| {
+        flags
+            .iter()
+            .rev()
+            .find(|(f, _)| f == name)
+            .map(|(_, v)| PathBuf::from(v))
+    };
+
+    let mut config = match flag("--config") {
+        Some(path) => storage::load::<Config>(path.as_path())
+            .ok_or_else(|| format!("failed to read {:?}", path))?,
+
+        None => flag("--data-dir")
+            .or_else(default_data_dir)
+            .and_then(|dir| storage::load::<Config>(dir.join(CONFIG_FILE_NAME).as_path()))
+            .unwrap_or_default(),
+    };
+
+    for (flag, value) in flags.iter() {
+        config.apply(flag, value)?;
+    }
+
+    Ok(config)
+}
+
+fn default_data_dir() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+// "s N" skips N tweets, the one being read included
+fn parse_skip(key: &str) -> Option<usize> {
+    let count = key.strip_prefix('s')?.trim().parse::<usize>().ok()?;
+
+    if count > 0 {
+        Some(count)
+    } else {
+        None
+    }
+}
+
+fn start_keyboard() -> tokio::sync::mpsc::Receiver<String> {
+    let (key_tx, key_rx) = tokio::sync::mpsc::channel::<String>(8);
+
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            match line {
+                Ok(line) => {
+                    if key_tx.blocking_send(line).is_err() {
+                        return;
+                    }
+                }
+                Err(_) => return,
+            }
+        }
+    });
+
+    key_rx
+}
+
+// Logs each tweet when it starts being read
+fn start_display(mut display_rx: tokio::sync::mpsc::Receiver<display_bridge::DisplayContrl>) {
+    tokio::spawn(async move {
+        let mut tweets = HashMap::<String, display_bridge::ViewElements>::new();
+
+        while let Some(msg) = display_rx.recv().await {
+            match msg {
+                display_bridge::DisplayContrl::Add(_, ve) => {
+                    tweets.insert(ve.tweet_id.clone(), ve);
+                }
+
+                display_bridge::DisplayContrl::Delete(_, twid) => {
+                    tweets.remove(&twid);
+                }
+
+                // Tweets are logged as they are read, in no list
+                display_bridge::DisplayContrl::Move(..) => {}
+
+                display_bridge::DisplayContrl::Scroll(_, twid) => {
+                    if let Some(ve) = tweets.get(&twid) {
+                        println!(">> {} (@{}) {}: {}", ve.name, ve.username, ve.created_at, ve.text);
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match load_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    println!("headless: {:?}", config);
+    println!("headless: audio devices {:?}", audio_sink::devices());
+
+    let data_dir = match config.data_dir.clone().or_else(default_data_dir) {
+        Some(dir) => dir,
+        None => {
+            eprintln!("no data directory, give one with --data-dir");
+            std::process::exit(2);
+        }
+    };
+    let host = host::Host::headless(data_dir);
+
+    let (authctl_tx, authctl_rx) =
+        tokio::sync::mpsc::channel::<twitter_authorizator::AuthControl>(1);
+
+    let (display_tx, display_rx) =
+        tokio::sync::mpsc::channel::<display_bridge::DisplayContrl>(QUEUE_LENGTH);
+
+    let (playbook_tx, playbook_rx) = tokio::sync::mpsc::channel::<voicegen_agent::Playbook>(1);
+
+    let (translated_tx, translated_rx) = tokio::sync::mpsc::channel::<voicegen_agent::Playbook>(1);
+
+    let (speech_tx, speech_rx) = tokio::sync::mpsc::channel::<voicegen_agent::TTSResult>(QUEUE_LENGTH);
+
+    let (timeline_tx, timeline_rx) = tokio::sync::mpsc::channel::<twitter_agent::Timeline>(1);
+
+    let (audioctl_tx, audioctl_rx) =
+        tokio::sync::mpsc::channel::<audio_player::AudioControl>(QUEUE_LENGTH);
+
+    let (audioctl_rdy_tx, audioctl_rdy_rx) =
+        tokio::sync::mpsc::channel::<audio_player::AudioControlRdy>(1);
+
+    let (user_tx, user_rx) = tokio::sync::mpsc::channel::<user_input::UserInput>(QUEUE_LENGTH);
+
+    let (recorder_tx, recorder_rx) =
+        tokio::sync::mpsc::channel::<recorder::RecorderControl>(QUEUE_LENGTH);
+
+    let rules = author_rules::AuthorRules::load(host.path(author_rules::FILE_NAME));
+    user_tx.send(user_input::UserInput::AuthorRules(rules.rules())).await.unwrap();
+
+    let mute = mute_rules::MuteRulesStore::load(host.path(mute_rules::FILE_NAME));
+    user_tx.send(user_input::UserInput::MuteRules(mute.rules())).await.unwrap();
+
+    let priority = priority_rules::PriorityRulesStore::load(host.path(priority_rules::FILE_NAME));
+    user_tx.send(user_input::UserInput::PriorityRules(priority.rules())).await.unwrap();
+
+    let speaker = voicegen_observer::Speaker {
+        addr: config.engine,
+        engine: "".to_string(),
+        name: "".to_string(),
+        style: "".to_string(),
+        speaker: config.speaker,
+    };
+    user_tx.send(user_input::UserInput::Speaker(speaker)).await.unwrap();
+    user_tx.send(user_input::UserInput::SpeechRate(config.speech_rate)).await.unwrap();
+    user_tx.send(user_input::UserInput::TimelineView(config.timeline())).await.unwrap();
+    user_tx.send(user_input::UserInput::Recording(config.record.clone())).await.unwrap();
+
+    let token_rx = twitter_authorizator::start(host.clone(), authctl_rx);
+    let (user_tl_rx, search_tl_rx) =
+        twitter_agent::start(host.clone(), authctl_tx.clone(), token_rx, timeline_rx);
+    let speakers_rx = voicegen_observer::start(host.clone());
+
+    scheduler::start(
+        host.clone(),
+        display_tx,
+        playbook_tx,
+        audioctl_tx.clone(),
+        recorder_tx,
+        user_tl_rx,
+        search_tl_rx,
+        speech_rx,
+        audioctl_rdy_rx,
+        user_rx,
+        speakers_rx,
+    );
+
+    start_display(display_rx);
+    translator::start(host.clone(), playbook_rx, translated_tx);
+    voicegen_agent::start(host.clone(), translated_rx, speech_tx);
+    recorder::start(host.clone(), recorder_rx);
+    audio_player::start(host, config.output.clone(), audioctl_rx, audioctl_rdy_tx);
+
+    audioctl_tx.send(audio_player::AudioControl::Volume(config.volume)).await.unwrap();
+    audioctl_tx.send(audio_player::AudioControl::Speed(config.playback_speed)).await.unwrap();
+    audioctl_tx.send(audio_player::AudioControl::Cues(config.cues.clone())).await.unwrap();
+    audioctl_tx.send(audio_player::AudioControl::Loudness(config.loudness.clone())).await.unwrap();
+    timeline_tx.send(config.timeline()).await.unwrap();
+    authctl_tx.send(twitter_authorizator::AuthControl::Authorize).await.unwrap();
+
+    println!("{}", KEYS);
+    let mut key_rx = start_keyboard();
+    let mut paused = false;
+    let mut volume = config.volume;
+    let mut speed = config.playback_speed.clamp(time_stretch::SPEED_MIN, time_stretch::SPEED_MAX);
+
+    loop {
+        // Keep reading when started without a terminal
+        let key = match key_rx.recv().await {
+            Some(key) => key,
+            None => std::future::pending::<String>().await,
+        };
+
+        match key.trim() {
+            "p" => {
+                paused = !paused;
+                println!("headless: {}", if paused { "paused" } else { "resumed" });
+
+                let ctl = if paused {
+                    audio_player::AudioControl::Pause
+                } else {
+                    audio_player::AudioControl::Resume
+                };
+                audioctl_tx.send(ctl).await.unwrap();
+                user_tx.send(user_input::UserInput::Paused(paused)).await.unwrap();
+            }
+
+            "n" => user_tx.send(user_input::UserInput::Next).await.unwrap(),
+            "b" => user_tx.send(user_input::UserInput::Previous).await.unwrap(),
+            "r" => user_tx.send(user_input::UserInput::RepeatLast).await.unwrap(),
+            "c" => user_tx.send(user_input::UserInput::ClearQueue).await.unwrap(),
+
+            "+" | "-" => {
+                volume = if key.trim() == "+" {
+                    (volume + VOLUME_STEP).min(100)
+                } else {
+                    volume.saturating_sub(VOLUME_STEP)
+                };
+                println!("headless: volume {}", volume);
+                audioctl_tx.send(audio_player::AudioControl::Volume(volume)).await.unwrap();
+            }
+
+            ">" | "<" => {
+                let step = if key.trim() == ">" { SPEED_STEP } else { -SPEED_STEP };
+                // Kept to steps, away from rounding errors
+                speed = (((speed + step) / SPEED_STEP).round() * SPEED_STEP)
+                    .clamp(time_stretch::SPEED_MIN, time_stretch::SPEED_MAX);
+                println!("headless: speed {:.1}", speed);
+                audioctl_tx.send(audio_player::AudioControl::Speed(speed)).await.unwrap();
+            }
+
+            "q" => {
+                println!("headless: quit");
+                let _ = audioctl_tx.send(audio_player::AudioControl::Quit).await;
+                return;
+            }
+
+            other => match parse_skip(other) {
+                Some(count) => {
+                    println!("headless: skip {}", count);
+                    user_tx.send(user_input::UserInput::Skip(count)).await.unwrap();
+                }
+                None => println!("{}", KEYS),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(s: &[&str]) -> Vec<String> {
+        s.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn ts_flags() {
+        let dir = std::env::temp_dir().join("twradio_headless_test");
+        let mut args = args(&["--data-dir", dir.to_str().unwrap(), "--search", "rust", "--volume", "120", "--speed", "1.5", "--output", "file:out.wav"]);
+
+        let config = load_config(&args).unwrap();
+        assert_eq!(config.data_dir, Some(dir));
+        assert_eq!(config.volume, 100);
+        assert_eq!(config.playback_speed, 1.5);
+        assert_eq!(config.output, audio_sink::Target::File(PathBuf::from("out.wav")));
+        assert_eq!(parse_output("Speakers"), audio_sink::Target::Device("Speakers".to_string()));
+        assert_eq!(config.timeline(), twitter_agent::Timeline::Search { query: "rust".to_string() });
+
+        args.push("--rate".to_string());
+        assert!(load_config(&args).is_err());
+        args.push("fast".to_string());
+        assert!(load_config(&args).is_err());
+        assert!(load_config(&["--unknown".to_string(), "1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn ts_parse_skip() {
+        assert_eq!(parse_skip("s 5"), Some(5));
+        assert_eq!(parse_skip("s10"), Some(10));
+        assert_eq!(parse_skip("s"), None);
+        assert_eq!(parse_skip("s 0"), None);
+        assert_eq!(parse_skip("x 5"), None);
+    }
+}
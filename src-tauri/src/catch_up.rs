@@ -0,0 +1,102 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CatchUpMode {
+    Off,
+    // Raise the speech rate the further behind we are
+    SpeedUp,
+    // Read the author and the first sentence only
+    Headlines,
+    // Announce the skipped tweets and jump ahead
+    SkipAhead,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CatchUpSettings {
+    pub mode: CatchUpMode,
+    // Queued tweets before catching up
+    pub threshold: usize,
+    pub max_speech_rate: f64,
+}
+
+impl CatchUpSettings {
+    pub fn new() -> Self {
+        CatchUpSettings {
+            mode: CatchUpMode::Off,
+            threshold: 32,
+            max_speech_rate: 2.0,
+        }
+    }
+
+    pub fn is_behind(&self, backlog: usize) -> bool {
+        self.mode != CatchUpMode::Off && self.threshold > 0 && backlog > self.threshold
+    }
+
+    // Grows linearly with the backlog, doubling at twice the threshold
+    pub fn speech_rate(&self, speech_rate: f64, backlog: usize) -> f64 {
+        if self.mode != CatchUpMode::SpeedUp || !self.is_behind(backlog) {
+            return speech_rate;
+        }
+
+        let over = (backlog - self.threshold) as f64 / self.threshold as f64;
+        let rate = speech_rate * (1.0 + over);
+        rate.min(self.max_speech_rate.max(speech_rate))
+    }
+
+    pub fn is_headlines(&self, backlog: usize) -> bool {
+        self.mode == CatchUpMode::Headlines && self.is_behind(backlog)
+    }
+
+    // Tweets to skip so that half of the threshold remains
+    pub fn skip_count(&self, backlog: usize) -> usize {
+        if self.mode != CatchUpMode::SkipAhead || !self.is_behind(backlog) {
+            return 0;
+        }
+
+        backlog - self.threshold / 2
+    }
+}
+
+pub fn headline(text: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref RE: Regex = Regex::new(r"^.*?([。！？!?\n]|\.\s|$)").unwrap();
+    }
+
+    match RE.find(text.trim()) {
+        Some(m) => m.as_str().trim().to_string(),
+        None => text.to_string(),
+    }
+}
+
+pub fn announcement(count: usize, from: &str) -> String {
+    format!("{}さんのツイートから、{}件をスキップしました", from, count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ts_headline() {
+        assert_eq!(headline("おはよう。今日は晴れ"), "おはよう。");
+        assert_eq!(headline("Good morning. It is sunny"), "Good morning.");
+        assert_eq!(headline("first line\nsecond line"), "first line");
+        assert_eq!(headline("no delimiter"), "no delimiter");
+    }
+
+    #[test]
+    fn ts_speech_rate_and_skip() {
+        let mut settings = CatchUpSettings::new();
+        settings.mode = CatchUpMode::SpeedUp;
+
+        assert_eq!(settings.speech_rate(1.0, 32), 1.0);
+        assert_eq!(settings.speech_rate(1.0, 48), 1.5);
+        assert_eq!(settings.speech_rate(1.0, 100), 2.0);
+        assert_eq!(settings.skip_count(100), 0);
+
+        settings.mode = CatchUpMode::SkipAhead;
+        assert_eq!(settings.skip_count(40), 24);
+        assert_eq!(settings.speech_rate(1.0, 40), 1.0);
+    }
+}
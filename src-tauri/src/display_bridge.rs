@@ -1,6 +1,7 @@
 use crate::scheduler;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "app")]
 use tauri::Manager;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +14,8 @@ pub struct ViewElements {
     pub username: String,
     pub profile_image_url: String,
     pub attachments: Vec<(String, String)>,
+    pub muted: bool,
+    pub priority: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +23,14 @@ pub enum DisplayContrl {
     Add(String, ViewElements),
     Scroll(String, String),
     Delete(String, String),
+    // Tweet id placed right after the other one, or first without one
+    Move(String, String, Option<String>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Move {
+    tweet_id: String,
+    after: Option<String>,
 }
 
 impl From<scheduler::Record> for ViewElements {
@@ -33,10 +44,13 @@ impl From<scheduler::Record> for ViewElements {
             username: record.username,
             profile_image_url: record.profile_image_url,
             attachments: record.attachments,
+            muted: false,
+            priority: record.priority,
         }
     }
 }
 
+#[cfg(feature = "app")]
 pub fn start(
     app_handle: tauri::AppHandle,
     mut display_rx: tokio::sync::mpsc::Receiver<DisplayContrl>,
@@ -59,6 +73,13 @@ pub fn start(
                             .unwrap();
                     }
 
+                    DisplayContrl::Move(name, twid, after) => {
+                        let url = format!("tauri://frontend/display/{name}/move");
+                        app_handle
+                            .emit_all(url.as_str(), Move { tweet_id: twid, after })
+                            .unwrap();
+                    }
+
                     DisplayContrl::Scroll(name, twid) => {
                         let url = format!("tauri://frontend/display/{name}/scroll");
                         app_handle
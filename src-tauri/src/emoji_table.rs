@@ -0,0 +1,270 @@
+// Japanese readings of frequently used emoji, taken from the CLDR short names.
+// Keys are stored without variation selectors and skin tone modifiers.
+pub const EMOJI_READINGS: &[(&str, &str)] = &[
+    // Smileys & Emotion
+    ("😀", "にっこり笑う顔"),
+    ("😃", "大きく目を開けて笑う顔"),
+    ("😄", "目を細めて笑う顔"),
+    ("😁", "歯を見せて笑う顔"),
+    ("😆", "目を閉じて笑う顔"),
+    ("😅", "冷や汗をかいて笑う顔"),
+    ("🤣", "笑い転げる顔"),
+    ("😂", "泣き笑いの顔"),
+    ("🙂", "少し笑う顔"),
+    ("🙃", "逆さまの顔"),
+    ("😉", "ウインクした顔"),
+    ("😊", "目を細めて微笑む顔"),
+    ("😇", "天使の顔"),
+    ("🥰", "ハートに囲まれた笑顔"),
+    ("😍", "ハートの目の笑顔"),
+    ("🤩", "目が星の顔"),
+    ("😘", "投げキッスする顔"),
+    ("😋", "おいしい顔"),
+    ("😛", "舌を出した顔"),
+    ("😜", "舌を出してウインクする顔"),
+    ("🤪", "おどけた顔"),
+    ("🤔", "考える顔"),
+    ("🤗", "ハグする顔"),
+    ("🤭", "口に手を当てた顔"),
+    ("🤫", "しーっとする顔"),
+    ("😐", "無表情の顔"),
+    ("😑", "呆れた顔"),
+    ("😶", "口のない顔"),
+    ("😏", "にやりとした顔"),
+    ("😒", "不満な顔"),
+    ("🙄", "目を回す顔"),
+    ("😬", "しかめっ面"),
+    ("😌", "ほっとした顔"),
+    ("😔", "しょんぼりした顔"),
+    ("😪", "眠そうな顔"),
+    ("😴", "寝ている顔"),
+    ("😷", "マスクの顔"),
+    ("🤒", "熱のある顔"),
+    ("🤢", "吐き気の顔"),
+    ("🥵", "暑い顔"),
+    ("🥶", "寒い顔"),
+    ("😵", "目を回した顔"),
+    ("🤯", "頭が爆発した顔"),
+    ("🥳", "パーティーの顔"),
+    ("😎", "サングラスの笑顔"),
+    ("🤓", "オタクの顔"),
+    ("😕", "困った顔"),
+    ("😟", "心配な顔"),
+    ("🙁", "少ししかめた顔"),
+    ("😮", "口を開けた顔"),
+    ("😲", "驚いた顔"),
+    ("😳", "赤くなった顔"),
+    ("🥺", "うるうるした顔"),
+    ("😦", "口を開けてしかめた顔"),
+    ("😨", "青ざめた顔"),
+    ("😰", "冷や汗の青ざめた顔"),
+    ("😥", "がっかりしつつほっとした顔"),
+    ("😢", "泣き顔"),
+    ("😭", "大泣きの顔"),
+    ("😱", "恐怖で叫ぶ顔"),
+    ("😖", "混乱した顔"),
+    ("😣", "我慢する顔"),
+    ("😞", "がっかりした顔"),
+    ("😓", "冷や汗の顔"),
+    ("😩", "疲れた顔"),
+    ("😫", "へとへとの顔"),
+    ("🥱", "あくびする顔"),
+    ("😤", "鼻息の荒い顔"),
+    ("😡", "ふくれっ面"),
+    ("😠", "怒った顔"),
+    ("🤬", "ののしる顔"),
+    ("😈", "笑う悪魔"),
+    ("💀", "ドクロ"),
+    ("💩", "うんち"),
+    ("🤡", "ピエロの顔"),
+    ("👻", "おばけ"),
+    ("👽", "宇宙人"),
+    ("🤖", "ロボット"),
+    ("😺", "笑う猫"),
+    ("🙈", "見ざる"),
+    ("🙉", "聞かざる"),
+    ("🙊", "言わざる"),
+    ("💋", "キスマーク"),
+    ("💯", "百点満点"),
+    ("💢", "怒りマーク"),
+    ("💥", "衝突"),
+    ("💦", "汗"),
+    ("💨", "ダッシュ"),
+    ("💤", "ぐーぐー"),
+    ("💬", "吹き出し"),
+    // Hearts
+    ("❤", "赤いハート"),
+    ("🧡", "オレンジのハート"),
+    ("💛", "黄色いハート"),
+    ("💚", "緑のハート"),
+    ("💙", "青いハート"),
+    ("💜", "紫のハート"),
+    ("🖤", "黒いハート"),
+    ("🤍", "白いハート"),
+    ("💔", "割れたハート"),
+    ("💕", "2つのハート"),
+    ("💞", "回転するハート"),
+    ("💓", "ドキドキするハート"),
+    ("💗", "大きくなるハート"),
+    ("💖", "キラキラハート"),
+    ("💘", "矢の刺さったハート"),
+    ("💝", "リボン付きハート"),
+    // People & Body
+    ("👋", "手を振る"),
+    ("👌", "オーケー"),
+    ("✌", "ピース"),
+    ("🤞", "指をクロス"),
+    ("👍", "いいね"),
+    ("👎", "よくないね"),
+    ("👏", "拍手"),
+    ("🙌", "ばんざい"),
+    ("🙏", "お願い"),
+    ("💪", "力こぶ"),
+    ("👀", "目"),
+    ("👉", "右指差し"),
+    ("👈", "左指差し"),
+    ("👆", "上指差し"),
+    ("👇", "下指差し"),
+    ("✋", "手のひら"),
+    ("👊", "げんこつ"),
+    ("🤝", "握手"),
+    ("🙇", "お辞儀"),
+    ("🤷", "肩をすくめる人"),
+    ("🤦", "顔に手を当てる人"),
+    // Animals & Nature
+    ("🐶", "犬の顔"),
+    ("🐱", "猫の顔"),
+    ("🐈", "猫"),
+    ("🐕", "犬"),
+    ("🐰", "うさぎの顔"),
+    ("🐻", "くまの顔"),
+    ("🐼", "パンダ"),
+    ("🐸", "カエル"),
+    ("🐧", "ペンギン"),
+    ("🐦", "鳥"),
+    ("🌸", "桜"),
+    ("🌹", "バラ"),
+    ("🌻", "ひまわり"),
+    ("🍀", "四つ葉"),
+    ("🍁", "もみじ"),
+    ("☀", "太陽"),
+    ("🌙", "三日月"),
+    ("⭐", "星"),
+    ("🌟", "輝く星"),
+    ("☁", "雲"),
+    ("☔", "傘と雨"),
+    ("⚡", "高電圧"),
+    ("❄", "雪の結晶"),
+    ("🔥", "炎"),
+    ("🌈", "虹"),
+    ("🌊", "波"),
+    // Food & Drink
+    ("🍎", "りんご"),
+    ("🍓", "いちご"),
+    ("🍙", "おにぎり"),
+    ("🍚", "ご飯"),
+    ("🍜", "ラーメン"),
+    ("🍣", "寿司"),
+    ("🍕", "ピザ"),
+    ("🍔", "ハンバーガー"),
+    ("🍰", "ショートケーキ"),
+    ("🎂", "バースデーケーキ"),
+    ("🍩", "ドーナツ"),
+    ("🍺", "ビール"),
+    ("🍻", "乾杯"),
+    ("🍷", "ワイン"),
+    ("☕", "ホットコーヒー"),
+    ("🍵", "お茶"),
+    // Activities & Objects
+    ("🎉", "クラッカー"),
+    ("🎊", "くす玉"),
+    ("🎁", "プレゼント"),
+    ("🎄", "クリスマスツリー"),
+    ("🎮", "ゲーム"),
+    ("🎵", "音符"),
+    ("🎶", "音符"),
+    ("🎤", "マイク"),
+    ("🎧", "ヘッドホン"),
+    ("📷", "カメラ"),
+    ("📸", "フラッシュ付きカメラ"),
+    ("📺", "テレビ"),
+    ("📱", "携帯電話"),
+    ("💻", "ノートパソコン"),
+    ("📢", "拡声器"),
+    ("📣", "メガホン"),
+    ("🔔", "ベル"),
+    ("📝", "メモ"),
+    ("📚", "本"),
+    ("💡", "電球"),
+    ("💰", "お金の袋"),
+    ("🏆", "トロフィー"),
+    ("🚀", "ロケット"),
+    ("🚃", "電車"),
+    ("🚗", "車"),
+    ("✈", "飛行機"),
+    ("⏰", "目覚まし時計"),
+    ("⌛", "砂時計"),
+    // Symbols
+    ("✨", "キラキラ"),
+    ("⚠", "警告"),
+    ("✅", "チェックマーク"),
+    ("☑", "チェック付きボックス"),
+    ("✔", "チェック"),
+    ("❌", "バツ"),
+    ("❗", "びっくりマーク"),
+    ("❓", "はてなマーク"),
+    ("‼", "二重びっくりマーク"),
+    ("⁉", "びっくりはてなマーク"),
+    ("⭕", "丸"),
+    ("🆕", "ニュー"),
+    ("🆗", "オーケー"),
+    ("🆙", "アップ"),
+    ("🈁", "ここ"),
+    ("🉐", "得"),
+    ("㊗", "祝"),
+    ("㊙", "秘"),
+    ("🔴", "赤い丸"),
+    ("🔵", "青い丸"),
+    ("➡", "右矢印"),
+    ("⬅", "左矢印"),
+    ("⬆", "上矢印"),
+    ("⬇", "下矢印"),
+    ("▶", "再生"),
+    ("©", "コピーライト"),
+    ("®", "登録商標"),
+    ("™", "トレードマーク"),
+    // Flags
+    ("🇯🇵", "日本の国旗"),
+    ("🇺🇸", "アメリカの国旗"),
+    ("🇬🇧", "イギリスの国旗"),
+    ("🇰🇷", "韓国の国旗"),
+    ("🇨🇳", "中国の国旗"),
+    ("🇫🇷", "フランスの国旗"),
+    ("🇩🇪", "ドイツの国旗"),
+];
+
+// Kaomoji and text symbols, matched as plain substrings with the longest taking precedence.
+pub const KAOMOJI_READINGS: &[(&str, &str)] = &[
+    ("(´・ω・`)", "しょぼん"),
+    ("(´；ω；`)", "ぶわっ"),
+    ("(^_^;)", "あせあせ"),
+    ("(^^;)", "あせあせ"),
+    ("(^^;", "あせあせ"),
+    ("(^_^)", "にこにこ"),
+    ("(^^)", "にこにこ"),
+    ("(*^^*)", "にこにこ"),
+    ("(^o^)", "わーい"),
+    ("＼(^o^)／", "ばんざい"),
+    ("\\(^o^)/", "ばんざい"),
+    ("m(_ _)m", "ぺこり"),
+    ("(T_T)", "しくしく"),
+    ("(;_;)", "しくしく"),
+    ("(・∀・)", "にやにや"),
+    ("( ´∀｀)", "にこにこ"),
+    ("(｀・ω・´)", "きりっ"),
+    ("(￣▽￣)", "にやり"),
+    ("orz", "がっくり"),
+    ("♪", "おんぷ"),
+    ("★", "ほし"),
+    ("☆", "ほし"),
+];
@@ -0,0 +1,151 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::name_cache;
+use crate::storage;
+use crate::user_dict;
+
+use twitter_v2::authorization::Oauth2Token;
+
+#[cfg(feature = "app")]
+use tauri::Manager;
+
+// Token of the headless reader; the app keeps it in the webview
+const TOKEN_FILE_NAME: &str = "twitter_token.json";
+
+// Where the agents run: inside the Tauri app, or headless on a terminal.
+#[derive(Clone)]
+pub enum Host {
+    #[cfg(feature = "app")]
+    App(tauri::AppHandle),
+    Headless(std::sync::Arc<Headless>),
+}
+
+// What the Tauri app keeps in its managed state
+pub struct Headless {
+    data_dir: PathBuf,
+    user_dict: tokio::sync::Mutex<user_dict::UserDict>,
+    name_cache: tokio::sync::Mutex<name_cache::NameCache>,
+}
+
+impl Host {
+    pub fn headless(data_dir: PathBuf) -> Self {
+        let user_dict = user_dict::UserDict::load(Some(data_dir.join(user_dict::FILE_NAME)));
+        let name_cache = name_cache::NameCache::load(Some(data_dir.join(name_cache::DIR_NAME)));
+
+        Host::Headless(std::sync::Arc::new(Headless {
+            data_dir,
+            user_dict: tokio::sync::Mutex::new(user_dict),
+            name_cache: tokio::sync::Mutex::new(name_cache),
+        }))
+    }
+
+    pub fn path(&self, name: &str) -> Option<PathBuf> {
+        match self {
+            #[cfg(feature = "app")]
+            Host::App(app_handle) => storage::path(app_handle, name),
+            Host::Headless(h) => Some(h.data_dir.join(name)),
+        }
+    }
+
+    // Events for the webview. Without one they are logged unless empty.
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        match self {
+            #[cfg(feature = "app")]
+            Host::App(app_handle) => app_handle.emit_all(event, payload).unwrap(),
+            Host::Headless(_) => {
+                let json = serde_json::to_string(&payload).unwrap_or_default();
+                if json != "\"\"" && json != "null" {
+                    println!("{}: {}", event, json);
+                }
+            }
+        }
+    }
+
+    // Payload of the next event from the webview. Headless, it resolves at once with none.
+    #[cfg_attr(not(feature = "app"), allow(unused_variables))]
+    pub fn once(&self, event: &str) -> tokio::sync::oneshot::Receiver<Option<String>> {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Option<String>>();
+
+        match self {
+            #[cfg(feature = "app")]
+            Host::App(app_handle) => {
+                app_handle.once_global(event, move |event| {
+                    let _ = tx.send(event.payload().map(|p| p.to_string()));
+                });
+            }
+            Host::Headless(_) => {
+                let _ = tx.send(None);
+            }
+        }
+
+        rx
+    }
+
+    pub fn user_dict(&self) -> &tokio::sync::Mutex<user_dict::UserDict> {
+        match self {
+            #[cfg(feature = "app")]
+            Host::App(app_handle) => app_handle
+                .state::<tokio::sync::Mutex<user_dict::UserDict>>()
+                .inner(),
+            Host::Headless(h) => &h.user_dict,
+        }
+    }
+
+    pub fn name_cache(&self) -> &tokio::sync::Mutex<name_cache::NameCache> {
+        match self {
+            #[cfg(feature = "app")]
+            Host::App(app_handle) => app_handle
+                .state::<tokio::sync::Mutex<name_cache::NameCache>>()
+                .inner(),
+            Host::Headless(h) => &h.name_cache,
+        }
+    }
+
+    pub async fn load_token(&self) -> Option<Oauth2Token> {
+        match self {
+            #[cfg(feature = "app")]
+            Host::App(_) => {
+                let token_response = self.once("tauri://backend/token-response");
+                self.emit("tauri://frontend/token-request", ());
+
+                match token_response.await.unwrap() {
+                    Some(payload) => serde_json::from_str(payload.as_str()).unwrap(),
+                    None => None,
+                }
+            }
+            Host::Headless(_) => storage::load(self.path(TOKEN_FILE_NAME)?.as_path()),
+        }
+    }
+
+    // Only the user can read the token file
+    pub fn save_token(&self, token: &Oauth2Token) {
+        match self {
+            #[cfg(feature = "app")]
+            Host::App(app_handle) => app_handle
+                .emit_all("tauri://frontend/token-register", token)
+                .unwrap(),
+            Host::Headless(_) => {
+                if let Some(path) = self.path(TOKEN_FILE_NAME) {
+                    if let Err(e) = storage::save_private(path.as_path(), token) {
+                        println!("host: failed to save token {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn remove_token(&self) {
+        match self {
+            #[cfg(feature = "app")]
+            Host::App(app_handle) => app_handle
+                .emit_all("tauri://frontend/token-unregister", ())
+                .unwrap(),
+            Host::Headless(_) => {
+                if let Some(path) = self.path(TOKEN_FILE_NAME) {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+    }
+}
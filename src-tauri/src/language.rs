@@ -0,0 +1,63 @@
+use regex::Regex;
+
+use crate::scheduler;
+
+pub const JAPANESE: &str = "ja";
+
+// Guess the language from the scripts used in the text.
+// Han characters without kana are treated as Japanese.
+pub fn detect(text: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref RE: Regex = Regex::new(r"https?://\S+|[@#]\w+|RT\s").unwrap();
+    }
+
+    let text = RE.replace_all(text, "");
+
+    let (mut kana, mut hangul, mut han, mut latin) = (0, 0, 0, 0);
+    for c in text.chars() {
+        match c {
+            '\u{3040}'..='\u{30FF}' | '\u{FF66}'..='\u{FF9F}' => kana += 1,
+            '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}' => {
+                hangul += 1
+            }
+            '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' => han += 1,
+            'a'..='z' | 'A'..='Z' => latin += 1,
+            _ => {}
+        }
+    }
+
+    if kana > 0 {
+        JAPANESE.to_string()
+    } else if hangul > 0 && hangul >= latin {
+        "ko".to_string()
+    } else if han > 0 {
+        JAPANESE.to_string()
+    } else if latin > 0 {
+        "en".to_string()
+    } else {
+        "und".to_string()
+    }
+}
+
+// Language given by twitter if any, detected otherwise
+pub fn resolve(record: &scheduler::Record) -> String {
+    match record.lang.as_str() {
+        "" | "und" | "qme" | "qht" | "zxx" => detect(record.text.as_str()),
+        lang => lang.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ts_detect() {
+        assert_eq!(detect("今日はいい天気ですね"), "ja");
+        assert_eq!(detect("東京都"), "ja");
+        assert_eq!(detect("오늘 날씨가 좋네요"), "ko");
+        assert_eq!(detect("What a nice day https://t.co/xxxx"), "en");
+        assert_eq!(detect("@user こんにちは"), "ja");
+        assert_eq!(detect("12345"), "und");
+    }
+}
@@ -0,0 +1,35 @@
+pub mod audio_cache;
+pub mod audio_player;
+pub mod audio_sink;
+pub mod author_rules;
+pub mod cancel_token;
+pub mod catch_up;
+pub mod display_bridge;
+pub mod emoji_table;
+pub mod host;
+pub mod language;
+pub mod loudness;
+pub mod mute_rules;
+pub mod name_cache;
+#[cfg(feature = "app")]
+pub mod open_browser_listener;
+pub mod priority_rules;
+pub mod recorder;
+pub mod replay_cache;
+pub mod scheduler;
+pub mod storage;
+pub mod time_stretch;
+pub mod translator;
+pub mod tweet_queue;
+pub mod twitter_agent;
+pub mod twitter_authorizator;
+pub mod twitter_client;
+pub mod twitter_data;
+pub mod user_dict;
+pub mod user_input;
+pub mod voicegen_agent;
+pub mod voicegen_client;
+pub mod voicegen_data;
+pub mod voicegen_filter;
+pub mod voicegen_observer;
+pub mod wav;
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Gated like LUFS (ITU-R BS.1770): 400 ms blocks every 100 ms,
+// blocks below an absolute and then a relative threshold left out.
+// There is no K-weighting beyond a high-pass against hum and DC,
+// which is close enough for comparing voices.
+const BLOCK_MS: u32 = 400;
+const HOP_MS: u32 = 100;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+const HIGH_PASS_HZ: f64 = 60.0;
+
+// Quiet speech is not raised into noise
+const GAIN_MAX: f64 = 20.0;
+const GAIN_MIN: f64 = -20.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LoudnessSettings {
+    pub enabled: bool,
+    // dB relative to full scale
+    pub target: f64,
+    // dB added to the speech of each engine, by engine name.
+    // Applied whether or not normalization is enabled.
+    pub engine_gain: HashMap<String, f64>,
+}
+
+impl LoudnessSettings {
+    // Off until turned on, so the speech plays at the level it always has
+    pub fn new() -> Self {
+        LoudnessSettings {
+            enabled: false,
+            target: -18.0,
+            engine_gain: HashMap::new(),
+        }
+    }
+
+    // Gain for samples in [-1, 1] as a factor, never clipping them
+    pub fn gain(&self, samples: &[f32], channels: u16, sample_rate: u32, engine: &str) -> f32 {
+        let mut db = self.engine_gain.get(engine).copied().unwrap_or(0.0);
+
+        if self.enabled {
+            if let Some(loudness) = measure(samples, channels, sample_rate) {
+                db += (self.target - loudness).clamp(GAIN_MIN, GAIN_MAX);
+            }
+        }
+
+        let peak = samples.iter().fold(0.0f32, |p, s| p.max(s.abs())) as f64;
+        let factor = 10f64.powf(db / 20.0);
+        if peak > 0.0 && factor * peak > 1.0 {
+            (1.0 / peak) as f32
+        } else {
+            factor as f32
+        }
+    }
+}
+
+// Mean square of each block, channels averaged
+fn block_powers(samples: &[f32], channels: u16, sample_rate: u32) -> Vec<f64> {
+    let channels = channels.max(1) as usize;
+    let frames = samples.len() / channels;
+
+    // First order high-pass on each channel
+    let rc = 1.0 / (2.0 * std::f64::consts::PI * HIGH_PASS_HZ);
+    let alpha = rc / (rc + 1.0 / sample_rate as f64);
+    let mut squares = vec![0.0f64; frames];
+    for c in 0..channels {
+        let (mut x_prev, mut y_prev) = (0.0f64, 0.0f64);
+        for (i, square) in squares.iter_mut().enumerate() {
+            let x = samples[i * channels + c] as f64;
+            let y = alpha * (y_prev + x - x_prev);
+            *square += y * y / channels as f64;
+            x_prev = x;
+            y_prev = y;
+        }
+    }
+
+    let block = (sample_rate * BLOCK_MS / 1000) as usize;
+    let hop = (sample_rate * HOP_MS / 1000) as usize;
+    if frames == 0 || block == 0 || hop == 0 {
+        return vec![];
+    }
+    // Utterances shorter than a block are a block of their own
+    if frames < block {
+        return vec![squares.iter().sum::<f64>() / frames as f64];
+    }
+
+    (0..=(frames - block) / hop)
+        .map(|i| squares[i * hop..i * hop + block].iter().sum::<f64>() / block as f64)
+        .collect()
+}
+
+fn to_db(power: f64) -> f64 {
+    10.0 * power.log10()
+}
+
+// dB relative to full scale, none for silence
+pub fn measure(samples: &[f32], channels: u16, sample_rate: u32) -> Option<f64> {
+    let powers: Vec<f64> = block_powers(samples, channels, sample_rate)
+        .into_iter()
+        .filter(|p| *p > 0.0 && to_db(*p) > ABSOLUTE_GATE)
+        .collect();
+    if powers.is_empty() {
+        return None;
+    }
+
+    let threshold = to_db(powers.iter().sum::<f64>() / powers.len() as f64) + RELATIVE_GATE;
+    let gated: Vec<f64> = powers.into_iter().filter(|p| to_db(*p) > threshold).collect();
+
+    Some(to_db(gated.iter().sum::<f64>() / gated.len() as f64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tone(amplitude: f32, ms: u32) -> Vec<f32> {
+        (0..24 * ms)
+            .map(|i| amplitude * (i as f32 * 2.0 * std::f32::consts::PI * 440.0 / 24000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn ts_measure() {
+        // Sine of amplitude 1 is at -3 dB
+        let loud = measure(&tone(1.0, 1000), 1, 24000).unwrap();
+        assert!((loud - -3.0).abs() < 0.2);
+
+        let quiet = measure(&tone(0.1, 1000), 1, 24000).unwrap();
+        assert!((loud - quiet - 20.0).abs() < 0.2);
+
+        // Pauses hardly make speech quieter, only blocks at their edges count
+        let mut paused = tone(0.1, 1000);
+        paused.extend(vec![0.0; 24000]);
+        paused.extend(tone(0.1, 1000));
+        assert!((measure(&paused, 1, 24000).unwrap() - quiet).abs() < 1.0);
+
+        assert_eq!(measure(&vec![0.0; 24000], 1, 24000), None);
+        assert_eq!(measure(&[], 2, 24000), None);
+        assert!((measure(&tone(0.1, 100), 1, 24000).unwrap() - quiet).abs() < 0.5);
+    }
+
+    #[test]
+    fn ts_gain() {
+        let mut settings = LoudnessSettings::new();
+        assert_eq!(settings.gain(&tone(0.01, 1000), 1, 24000, ""), 1.0);
+
+        settings.enabled = true;
+        settings.target = -23.0;
+
+        // 0.1 sine at -23 dB is left as is, the quiet one is raised to it
+        let gain = settings.gain(&tone(0.1, 1000), 1, 24000, "");
+        assert!((gain - 1.0).abs() < 0.05);
+        let gain = settings.gain(&tone(0.01, 1000), 1, 24000, "");
+        assert!((gain - 10.0).abs() < 0.5);
+
+        // Raised no further than the peak allows
+        settings.target = 0.0;
+        let gain = settings.gain(&tone(0.5, 1000), 1, 24000, "");
+        assert!((gain - 2.0).abs() < 0.01);
+
+        settings.enabled = false;
+        settings.engine_gain.insert("VOICEVOX".to_string(), -6.0);
+        assert_eq!(settings.gain(&tone(0.5, 1000), 1, 24000, "COEIROINK"), 1.0);
+        let gain = settings.gain(&tone(0.5, 1000), 1, 24000, "VOICEVOX");
+        assert!((gain - 0.5).abs() < 0.01);
+    }
+}
@@ -3,21 +3,31 @@
     windows_subsystem = "windows"
 )]
 
-mod audio_player;
-mod display_bridge;
-mod scheduler;
-mod open_browser_listener;
-mod twitter_agent;
-mod twitter_authorizator;
-mod twitter_client;
-mod twitter_data;
-mod user_input;
-mod voicegen_agent;
-mod voicegen_client;
-mod voicegen_data;
-mod voicegen_filter;
-mod voicegen_observer;
-
+use app::{
+    audio_player,
+    audio_sink,
+    author_rules,
+    catch_up,
+    display_bridge,
+    host,
+    loudness,
+    mute_rules,
+    name_cache,
+    open_browser_listener,
+    priority_rules,
+    recorder,
+    scheduler,
+    storage,
+    translator,
+    twitter_agent,
+    twitter_authorizator,
+    user_dict,
+    user_input,
+    voicegen_agent,
+    voicegen_client,
+    voicegen_filter,
+    voicegen_observer,
+};
 use tauri::Manager;
 
 // async command function must return Result to avoid issue
@@ -116,6 +126,86 @@ async fn set_volume(
     Ok(())
 }
 
+// Unlike the speech rate, nothing synthesized is thrown away
+#[tauri::command]
+async fn set_playback_speed(
+    speed: f64,
+    state: tauri::State<
+        '_,
+        tokio::sync::Mutex<tokio::sync::mpsc::Sender<audio_player::AudioControl>>,
+    >,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_playback_speed {:?}", speed);
+    tx.send(audio_player::AudioControl::Speed(speed))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_audio_cues(
+    cues: audio_player::CueSettings,
+    state: tauri::State<
+        '_,
+        tokio::sync::Mutex<tokio::sync::mpsc::Sender<audio_player::AudioControl>>,
+    >,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_audio_cues {:?}", cues);
+    tx.send(audio_player::AudioControl::Cues(cues))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_loudness(
+    settings: loudness::LoudnessSettings,
+    state: tauri::State<
+        '_,
+        tokio::sync::Mutex<tokio::sync::mpsc::Sender<audio_player::AudioControl>>,
+    >,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_loudness {:?}", settings);
+    tx.send(audio_player::AudioControl::Loudness(settings))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_audio_devices() -> Result<Vec<String>, ()> {
+    println!("tauri://backend/get_audio_devices");
+
+    Ok(audio_sink::devices())
+}
+
+#[tauri::command]
+async fn set_audio_output(
+    target: audio_sink::Target,
+    state: tauri::State<
+        '_,
+        tokio::sync::Mutex<tokio::sync::mpsc::Sender<audio_player::AudioControl>>,
+    >,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_audio_output {:?}", target);
+    tx.send(audio_player::AudioControl::Output(target))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
 #[tauri::command]
 async fn set_speaker(
     speaker: voicegen_observer::Speaker,
@@ -146,6 +236,350 @@ async fn set_speech_rate(
     Ok(())
 }
 
+#[tauri::command]
+async fn set_emoji_mode(
+    mode: voicegen_filter::EmojiMode,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_emoji_mode {:?}", mode);
+    tx.send(user_input::UserInput::EmojiMode(mode))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_emoji_overrides(
+    overrides: std::collections::HashMap<String, String>,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_emoji_overrides {:?}", overrides);
+    tx.send(user_input::UserInput::EmojiOverrides(overrides))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_user_dict(
+    dict: tauri::State<'_, tokio::sync::Mutex<user_dict::UserDict>>,
+) -> Result<Vec<user_dict::Word>, ()> {
+    let dict = dict.lock().await;
+
+    Ok(dict.words())
+}
+
+#[tauri::command]
+async fn add_user_dict_word(
+    word: user_dict::Word,
+    dict: tauri::State<'_, tokio::sync::Mutex<user_dict::UserDict>>,
+    name_cache: tauri::State<'_, tokio::sync::Mutex<name_cache::NameCache>>,
+    userin: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), String> {
+    println!("tauri://backend/add_user_dict_word {:?}", word);
+    {
+        let mut dict = dict.lock().await;
+        dict.add(word).map_err(|e| e.to_string())?;
+    }
+
+    name_cache.lock().await.clear();
+
+    let tx = userin.lock().await;
+    tx.send(user_input::UserInput::UserDictUpdated)
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn remove_user_dict_word(
+    surface: String,
+    dict: tauri::State<'_, tokio::sync::Mutex<user_dict::UserDict>>,
+    name_cache: tauri::State<'_, tokio::sync::Mutex<name_cache::NameCache>>,
+    userin: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), String> {
+    println!("tauri://backend/remove_user_dict_word {:?}", surface);
+    {
+        let mut dict = dict.lock().await;
+        dict.remove(surface.as_str()).map_err(|e| e.to_string())?;
+    }
+
+    name_cache.lock().await.clear();
+
+    let tx = userin.lock().await;
+    tx.send(user_input::UserInput::UserDictUpdated)
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+// Bring the engine's own dictionary in line with the plain (non regex) words:
+// missing words are added, changed ones updated and deleted ones removed.
+// Returns the number of changes made.
+#[tauri::command]
+async fn sync_user_dict(
+    speaker: voicegen_observer::Speaker,
+    dict: tauri::State<'_, tokio::sync::Mutex<user_dict::UserDict>>,
+    name_cache: tauri::State<'_, tokio::sync::Mutex<name_cache::NameCache>>,
+) -> Result<usize, String> {
+    println!("tauri://backend/sync_user_dict {:?}", speaker);
+
+    let registered = voicegen_client::request_user_dict(speaker.addr)
+        .await
+        .map_err(|_| format!("{} does not support user dictionary", speaker.engine))?;
+
+    let ops = dict.lock().await.sync_ops(speaker.addr, &registered);
+    let mut count = 0;
+    let mut not_added = vec![];
+    let mut not_deleted = vec![];
+    for op in ops.iter() {
+        let result = match op {
+            user_dict::SyncOp::Add(word) => voicegen_client::request_add_user_dict_word(
+                speaker.addr,
+                word.surface.as_str(),
+                user_dict::pronunciation(word.reading.as_str()).as_str(),
+                word.priority,
+            )
+            .await
+            .map(|_| ()),
+            user_dict::SyncOp::Update(uuid, word) => voicegen_client::request_update_user_dict_word(
+                speaker.addr,
+                uuid.as_str(),
+                word.surface.as_str(),
+                user_dict::pronunciation(word.reading.as_str()).as_str(),
+                word.priority,
+            )
+            .await,
+            user_dict::SyncOp::Delete(uuid, _) => {
+                voicegen_client::request_delete_user_dict_word(speaker.addr, uuid.as_str()).await
+            }
+        };
+
+        match result {
+            Ok(_) => count += 1,
+            Err(e) => {
+                println!("sync_user_dict: failed to {:?} {:?}", op, e);
+                match op {
+                    user_dict::SyncOp::Add(word) => not_added.push(word.surface.clone()),
+                    user_dict::SyncOp::Delete(_, surface) => not_deleted.push(surface.clone()),
+                    user_dict::SyncOp::Update(_, _) => {}
+                }
+            }
+        }
+    }
+
+    // Failed deletions are tried again on the next sync
+    {
+        let mut dict = dict.lock().await;
+        let mut synced: Vec<String> = dict
+            .words()
+            .into_iter()
+            .filter(|w| !w.regex && !not_added.contains(&w.surface))
+            .map(|w| w.surface)
+            .collect();
+        synced.extend(not_deleted);
+        dict.set_synced(speaker.addr, synced).map_err(|e| e.to_string())?;
+    }
+
+    if count > 0 {
+        name_cache.lock().await.invalidate_engine(speaker.addr);
+    }
+
+    Ok(count)
+}
+
+#[tauri::command]
+async fn get_author_rules(
+    rules: tauri::State<'_, tokio::sync::Mutex<author_rules::AuthorRules>>,
+) -> Result<Vec<author_rules::AuthorRule>, ()> {
+    let rules = rules.lock().await;
+
+    Ok(rules.rules().into_values().collect())
+}
+
+#[tauri::command]
+async fn set_author_rule(
+    rule: author_rules::AuthorRule,
+    rules: tauri::State<'_, tokio::sync::Mutex<author_rules::AuthorRules>>,
+    userin: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), String> {
+    println!("tauri://backend/set_author_rule {:?}", rule);
+    let snapshot = {
+        let mut rules = rules.lock().await;
+        rules.set(rule).map_err(|e| e.to_string())?;
+        rules.rules()
+    };
+
+    let tx = userin.lock().await;
+    tx.send(user_input::UserInput::AuthorRules(snapshot))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn remove_author_rule(
+    author_id: String,
+    rules: tauri::State<'_, tokio::sync::Mutex<author_rules::AuthorRules>>,
+    userin: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), String> {
+    println!("tauri://backend/remove_author_rule {:?}", author_id);
+    let snapshot = {
+        let mut rules = rules.lock().await;
+        rules.remove(author_id.as_str()).map_err(|e| e.to_string())?;
+        rules.rules()
+    };
+
+    let tx = userin.lock().await;
+    tx.send(user_input::UserInput::AuthorRules(snapshot))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_auto_voice(
+    auto_voice: scheduler::AutoVoice,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_auto_voice {:?}", auto_voice);
+    tx.send(user_input::UserInput::AutoVoice(auto_voice))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_mute_rules(
+    store: tauri::State<'_, tokio::sync::Mutex<mute_rules::MuteRulesStore>>,
+) -> Result<mute_rules::MuteRules, ()> {
+    let store = store.lock().await;
+
+    Ok(store.rules())
+}
+
+#[tauri::command]
+async fn set_mute_rules(
+    rules: mute_rules::MuteRules,
+    store: tauri::State<'_, tokio::sync::Mutex<mute_rules::MuteRulesStore>>,
+    userin: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), String> {
+    println!("tauri://backend/set_mute_rules {:?}", rules);
+    {
+        let mut store = store.lock().await;
+        store.set(rules.clone())?;
+    }
+
+    let tx = userin.lock().await;
+    tx.send(user_input::UserInput::MuteRules(rules))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_priority_rules(
+    store: tauri::State<'_, tokio::sync::Mutex<priority_rules::PriorityRulesStore>>,
+) -> Result<priority_rules::PriorityRules, ()> {
+    let store = store.lock().await;
+
+    Ok(store.rules())
+}
+
+#[tauri::command]
+async fn set_priority_rules(
+    rules: priority_rules::PriorityRules,
+    store: tauri::State<'_, tokio::sync::Mutex<priority_rules::PriorityRulesStore>>,
+    userin: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), String> {
+    println!("tauri://backend/set_priority_rules {:?}", rules);
+    {
+        let mut store = store.lock().await;
+        store.set(rules.clone()).map_err(|e| e.to_string())?;
+    }
+
+    let tx = userin.lock().await;
+    tx.send(user_input::UserInput::PriorityRules(rules))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_language_voices(
+    voices: std::collections::HashMap<String, voicegen_observer::Speaker>,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_language_voices {:?}", voices);
+    tx.send(user_input::UserInput::LanguageVoices(voices))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_translation(
+    translation: translator::TranslateSettings,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_translation {:?}", translation);
+    tx.send(user_input::UserInput::Translation(translation))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_recording(
+    settings: recorder::RecordSettings,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_recording {:?}", settings);
+    tx.send(user_input::UserInput::Recording(settings))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_catch_up(
+    catch_up: catch_up::CatchUpSettings,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    println!("tauri://backend/set_catch_up {:?}", catch_up);
+    tx.send(user_input::UserInput::CatchUp(catch_up))
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
 #[tauri::command]
 async fn jump(
     twid: &str,
@@ -161,6 +595,113 @@ async fn jump(
     Ok(())
 }
 
+#[tauri::command]
+async fn replay(
+    twid: &str,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    tx.send(user_input::UserInput::Replay(twid.to_string()))
+        .await
+        .unwrap();
+    println!("tauri://backend/replay {:?}", twid);
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn repeat_last(
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    tx.send(user_input::UserInput::RepeatLast).await.unwrap();
+    println!("tauri://backend/repeat_last");
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn previous(
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    tx.send(user_input::UserInput::Previous).await.unwrap();
+    println!("tauri://backend/previous");
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn next(
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    tx.send(user_input::UserInput::Next).await.unwrap();
+    println!("tauri://backend/next");
+
+    Ok(())
+}
+
+// Skip the current tweet and the following count - 1 tweets
+#[tauri::command]
+async fn skip(
+    count: usize,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    tx.send(user_input::UserInput::Skip(count)).await.unwrap();
+    println!("tauri://backend/skip {:?}", count);
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn remove_tweet(
+    twid: &str,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    tx.send(user_input::UserInput::Remove(twid.to_string()))
+        .await
+        .unwrap();
+    println!("tauri://backend/remove_tweet {:?}", twid);
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn move_to_front(
+    twid: &str,
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    tx.send(user_input::UserInput::MoveToFront(twid.to_string()))
+        .await
+        .unwrap();
+    println!("tauri://backend/move_to_front {:?}", twid);
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn clear_queue(
+    state: tauri::State<'_, tokio::sync::Mutex<tokio::sync::mpsc::Sender<user_input::UserInput>>>,
+) -> Result<(), ()> {
+    let tx = state.lock().await;
+
+    tx.send(user_input::UserInput::ClearQueue).await.unwrap();
+    println!("tauri://backend/clear_queue");
+
+    Ok(())
+}
+
 const QUEUE_LENGTH: usize = 256;
 
 #[tokio::main]
@@ -175,7 +716,9 @@ async fn main() -> std::io::Result<()> {
 
     let (playbook_tx, playbook_rx) = tokio::sync::mpsc::channel::<voicegen_agent::Playbook>(1);
 
-    let (speech_tx, speech_rx) = tokio::sync::mpsc::channel::<Option<voicegen_agent::Speech>>(1);
+    let (translated_tx, translated_rx) = tokio::sync::mpsc::channel::<voicegen_agent::Playbook>(1);
+
+    let (speech_tx, speech_rx) = tokio::sync::mpsc::channel::<voicegen_agent::TTSResult>(QUEUE_LENGTH);
 
     let (timeline_tx, timeline_rx) = tokio::sync::mpsc::channel::<twitter_agent::Timeline>(1);
 
@@ -187,9 +730,13 @@ async fn main() -> std::io::Result<()> {
 
     let (user_tx, user_rx) = tokio::sync::mpsc::channel::<user_input::UserInput>(QUEUE_LENGTH);
 
+    let (recorder_tx, recorder_rx) =
+        tokio::sync::mpsc::channel::<recorder::RecorderControl>(QUEUE_LENGTH);
+
     println!("twitter_authorizator::start");
 
     let authctl_tx_c = authctl_tx.clone();
+    let user_tx_c = user_tx.clone();
     let audioctl_tx_c = audioctl_tx.clone();
 
     tauri::Builder::default()
@@ -203,45 +750,75 @@ async fn main() -> std::io::Result<()> {
 
             let app_handle = app.app_handle();
 
-            let token_rx = twitter_authorizator::start(app_handle.clone(), authctl_rx);
+            let dict_path = storage::path(&app_handle, user_dict::FILE_NAME);
+            app.manage(tokio::sync::Mutex::new(user_dict::UserDict::load(dict_path)));
+
+            let name_cache_dir = storage::path(&app_handle, name_cache::DIR_NAME);
+            app.manage(tokio::sync::Mutex::new(name_cache::NameCache::load(name_cache_dir)));
+
+            let rules_path = storage::path(&app_handle, author_rules::FILE_NAME);
+            let rules = author_rules::AuthorRules::load(rules_path);
+            user_tx_c
+                .try_send(user_input::UserInput::AuthorRules(rules.rules()))
+                .unwrap();
+            app.manage(tokio::sync::Mutex::new(rules));
+
+            let mute_path = storage::path(&app_handle, mute_rules::FILE_NAME);
+            let mute = mute_rules::MuteRulesStore::load(mute_path);
+            user_tx_c
+                .try_send(user_input::UserInput::MuteRules(mute.rules()))
+                .unwrap();
+            app.manage(tokio::sync::Mutex::new(mute));
+
+            let priority_path = storage::path(&app_handle, priority_rules::FILE_NAME);
+            let priority = priority_rules::PriorityRulesStore::load(priority_path);
+            user_tx_c
+                .try_send(user_input::UserInput::PriorityRules(priority.rules()))
+                .unwrap();
+            app.manage(tokio::sync::Mutex::new(priority));
+
+            let host = host::Host::App(app_handle.clone());
+
+            let token_rx = twitter_authorizator::start(host.clone(), authctl_rx);
 
             println!("twitter_agent::start");
-            let (user_tl_rx, search_tl_rx) = twitter_agent::start(app_handle.clone(),
+            let (user_tl_rx, search_tl_rx) = twitter_agent::start(host.clone(),
                                                 authctl_tx.clone(),
                                                 token_rx,
                                                 timeline_rx);
 
             println!("voicegen_observer::start");
-            voicegen_observer::start(app_handle.clone());
+            let speakers_rx = voicegen_observer::start(host.clone());
 
             println!("scheduler::start");
             scheduler::start(
-                app_handle.clone(),
+                host.clone(),
                 display_tx.clone(),
                 playbook_tx.clone(),
                 audioctl_tx.clone(),
+                recorder_tx,
                 user_tl_rx,
                 search_tl_rx,
                 speech_rx,
                 audioctl_rdy_rx,
                 user_rx,
+                speakers_rx,
             );
 
             println!("display_bridge::start");
             display_bridge::start(app_handle.clone(), display_rx);
 
+            println!("translator::start");
+            translator::start(host.clone(), playbook_rx, translated_tx);
+
             println!("voicegen_agent::start");
-            voicegen_agent::start(app_handle.clone(), playbook_rx, speech_tx);
+            voicegen_agent::start(host.clone(), translated_rx, speech_tx);
+
+            println!("recorder::start");
+            recorder::start(host.clone(), recorder_rx);
 
             println!("audio_player::start");
-            audio_player::start(app_handle.clone(), audioctl_rx, audioctl_rdy_tx);
-            let tick_tx = audioctl_tx.clone();
-            tokio::spawn(async move {
-                loop {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                    let _ = tick_tx.send(audio_player::AudioControl::Tick).await;
-                }
-            });
+            audio_player::start(host, audio_sink::Target::Default, audioctl_rx, audioctl_rdy_tx);
 
             // open_browser_listener
             open_browser_listener::start(app_handle);
@@ -258,9 +835,40 @@ async fn main() -> std::io::Result<()> {
             set_timeline,
             set_timeline_view,
             set_volume,
+            set_playback_speed,
+            set_audio_cues,
+            set_loudness,
+            get_audio_devices,
+            set_audio_output,
             set_speaker,
             set_speech_rate,
-            jump
+            set_emoji_mode,
+            set_emoji_overrides,
+            get_user_dict,
+            add_user_dict_word,
+            remove_user_dict_word,
+            sync_user_dict,
+            get_author_rules,
+            set_author_rule,
+            remove_author_rule,
+            set_auto_voice,
+            get_mute_rules,
+            set_mute_rules,
+            get_priority_rules,
+            set_priority_rules,
+            set_language_voices,
+            set_translation,
+            set_catch_up,
+            set_recording,
+            jump,
+            replay,
+            repeat_last,
+            previous,
+            next,
+            skip,
+            remove_tweet,
+            move_to_front,
+            clear_queue
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,244 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::scheduler;
+use crate::storage;
+
+pub const FILE_NAME: &str = "mute_rules.json";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum MediaFilter {
+    Any,
+    SkipWithMedia,
+    OnlyWithMedia,
+}
+
+impl Default for MediaFilter {
+    fn default() -> Self {
+        MediaFilter::Any
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MuteRules {
+    pub words: Vec<String>,
+    pub regexes: Vec<String>,
+    // author_id or username
+    pub authors: Vec<String>,
+    pub skip_retweets: bool,
+    pub skip_replies: bool,
+    // Languages to read. Every language is read when empty.
+    pub languages: Vec<String>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub media: MediaFilter,
+    // Show muted tweets greyed out instead of hiding them
+    pub show_muted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MuteReason {
+    Word(String),
+    Regex(String),
+    Author,
+    Retweet,
+    Reply,
+    Language(String),
+    TooShort,
+    TooLong,
+    Media,
+}
+
+pub struct MuteFilter {
+    rules: MuteRules,
+    regexes: Vec<Regex>,
+}
+
+impl MuteFilter {
+    pub fn new(rules: MuteRules) -> Self {
+        let regexes = rules
+            .regexes
+            .iter()
+            .filter_map(|r| match Regex::new(r.as_str()) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    println!("mute_rules: invalid regex {:?} {:?}", r, e);
+                    None
+                }
+            })
+            .collect();
+
+        MuteFilter { rules, regexes }
+    }
+
+    pub fn show_muted(&self) -> bool {
+        self.rules.show_muted
+    }
+
+    pub fn check(&self, record: &scheduler::Record) -> Option<MuteReason> {
+        let rules = &self.rules;
+
+        if rules.authors.iter().any(|a| {
+            *a == record.author_id || a.trim_start_matches('@').eq_ignore_ascii_case(&record.username)
+        }) {
+            return Some(MuteReason::Author);
+        }
+
+        if rules.skip_retweets && record.is_retweet {
+            return Some(MuteReason::Retweet);
+        }
+
+        if rules.skip_replies && record.is_reply {
+            return Some(MuteReason::Reply);
+        }
+
+        // "und" is used by twitter when the language can not be determined
+        if !rules.languages.is_empty()
+            && !record.lang.is_empty()
+            && record.lang != "und"
+            && !rules.languages.contains(&record.lang)
+        {
+            return Some(MuteReason::Language(record.lang.clone()));
+        }
+
+        let length = record.text.chars().count();
+        if rules.min_length.map_or(false, |min| length < min) {
+            return Some(MuteReason::TooShort);
+        }
+
+        if rules.max_length.map_or(false, |max| length > max) {
+            return Some(MuteReason::TooLong);
+        }
+
+        let has_media = !record.attachments.is_empty();
+        match rules.media {
+            MediaFilter::SkipWithMedia if has_media => return Some(MuteReason::Media),
+            MediaFilter::OnlyWithMedia if !has_media => return Some(MuteReason::Media),
+            _ => {}
+        }
+
+        let text = record.text.to_lowercase();
+        if let Some(word) = rules
+            .words
+            .iter()
+            .find(|w| !w.is_empty() && text.contains(w.to_lowercase().as_str()))
+        {
+            return Some(MuteReason::Word(word.clone()));
+        }
+
+        if let Some(re) = self.regexes.iter().find(|re| re.is_match(record.text.as_str())) {
+            return Some(MuteReason::Regex(re.as_str().to_string()));
+        }
+
+        None
+    }
+}
+
+pub struct MuteRulesStore {
+    path: Option<PathBuf>,
+    rules: MuteRules,
+}
+
+impl MuteRulesStore {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let rules = path
+            .as_ref()
+            .and_then(|p| storage::load(p.as_path()))
+            .unwrap_or_default();
+
+        MuteRulesStore { path, rules }
+    }
+
+    pub fn rules(&self) -> MuteRules {
+        self.rules.clone()
+    }
+
+    pub fn set(&mut self, rules: MuteRules) -> Result<(), String> {
+        for r in &rules.regexes {
+            Regex::new(r.as_str()).map_err(|e| e.to_string())?;
+        }
+
+        self.rules = rules;
+        match self.path {
+            Some(ref path) => storage::save(path.as_path(), &self.rules).map_err(|e| e.to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(text: &str) -> scheduler::Record {
+        scheduler::Record {
+            tweet_id: "1".to_string(),
+            author_id: "100".to_string(),
+            created_at: "".to_string(),
+            text: text.to_string(),
+            name: "name".to_string(),
+            username: "user".to_string(),
+            profile_image_url: "".to_string(),
+            attachments: vec![],
+            lang: "ja".to_string(),
+            is_retweet: false,
+            is_reply: false,
+            priority: false,
+        }
+    }
+
+    #[test]
+    fn ts_check_words_and_regex() {
+        let filter = MuteFilter::new(MuteRules {
+            words: vec!["Spoiler".to_string()],
+            regexes: vec![r"^\d+$".to_string()],
+            ..Default::default()
+        });
+
+        assert_eq!(filter.check(&record("no spoiler please")), Some(MuteReason::Word("Spoiler".to_string())));
+        assert!(filter.check(&record("12345")).is_some());
+        assert!(filter.check(&record("こんにちは")).is_none());
+    }
+
+    #[test]
+    fn ts_check_author_and_type() {
+        let filter = MuteFilter::new(MuteRules {
+            authors: vec!["@User".to_string()],
+            skip_retweets: true,
+            ..Default::default()
+        });
+        assert_eq!(filter.check(&record("hello")), Some(MuteReason::Author));
+
+        let filter = MuteFilter::new(MuteRules {
+            skip_retweets: true,
+            ..Default::default()
+        });
+        let mut r = record("RT @someone: hello");
+        r.is_retweet = true;
+        assert_eq!(filter.check(&r), Some(MuteReason::Retweet));
+    }
+
+    #[test]
+    fn ts_check_language_length_media() {
+        let filter = MuteFilter::new(MuteRules {
+            languages: vec!["ja".to_string()],
+            min_length: Some(3),
+            media: MediaFilter::SkipWithMedia,
+            ..Default::default()
+        });
+
+        let mut r = record("hello");
+        r.lang = "en".to_string();
+        assert_eq!(filter.check(&r), Some(MuteReason::Language("en".to_string())));
+        r.lang = "und".to_string();
+        assert!(filter.check(&r).is_none());
+
+        assert_eq!(filter.check(&record("ok")), Some(MuteReason::TooShort));
+
+        let mut r = record("photo");
+        r.attachments.push(("photo".to_string(), "url".to_string()));
+        assert_eq!(filter.check(&r), Some(MuteReason::Media));
+    }
+}
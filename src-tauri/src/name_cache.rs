@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use crate::audio_cache;
+
+pub const DIR_NAME: &str = "name_cache";
+
+const CACHE_BYTES_MAX: u64 = 32 * 1024 * 1024;
+// Only short utterances such as names are worth caching
+const TEXT_LENGTH_MAX: usize = 32;
+
+// Synthesized author names, persisted across sessions.
+// Voice settings and the normalized text are part of the key, so changing them never hits stale audio.
+pub struct NameCache {
+    cache: audio_cache::AudioCache,
+}
+
+impl NameCache {
+    pub fn load(dir: Option<PathBuf>) -> Self {
+        NameCache {
+            cache: audio_cache::AudioCache::load(dir, CACHE_BYTES_MAX),
+        }
+    }
+
+    pub fn key(addr: std::net::SocketAddr, speaker: u64, speech_rate: f64, text: &str) -> Option<String> {
+        if text.chars().count() > TEXT_LENGTH_MAX {
+            return None;
+        }
+
+        Some(format!("{}/{}/{:.2}/{}", addr, speaker, speech_rate, text))
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<Vec<u8>> {
+        self.cache.get(key)
+    }
+
+    pub fn insert(&mut self, key: String, voice: &[u8]) {
+        self.cache.insert(key, voice)
+    }
+
+    // The engine may read the same text differently after its dictionary or voices changed
+    pub fn invalidate_engine(&mut self, addr: std::net::SocketAddr) {
+        println!("name_cache: invalidate {:?}", addr);
+        let prefix = format!("{}/", addr);
+        self.cache.remove_if(|key| key.starts_with(prefix.as_str()));
+    }
+
+    pub fn clear(&mut self) {
+        println!("name_cache: clear");
+        self.cache.clear();
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::scheduler;
+use crate::storage;
+
+pub const FILE_NAME: &str = "priority_rules.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PriorityRules {
+    pub keywords: Vec<String>,
+    // author_id or username
+    pub authors: Vec<String>,
+    // Play a chime before reading a priority tweet
+    pub chime: bool,
+}
+
+impl PriorityRules {
+    pub fn matches(&self, record: &scheduler::Record) -> bool {
+        if self.authors.iter().any(|a| {
+            *a == record.author_id || a.trim_start_matches('@').eq_ignore_ascii_case(&record.username)
+        }) {
+            return true;
+        }
+
+        let text = record.text.to_lowercase();
+        self.keywords
+            .iter()
+            .any(|k| !k.is_empty() && text.contains(k.to_lowercase().as_str()))
+    }
+}
+
+pub struct PriorityRulesStore {
+    path: Option<PathBuf>,
+    rules: PriorityRules,
+}
+
+impl PriorityRulesStore {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let rules = path
+            .as_ref()
+            .and_then(|p| storage::load(p.as_path()))
+            .unwrap_or_default();
+
+        PriorityRulesStore { path, rules }
+    }
+
+    pub fn rules(&self) -> PriorityRules {
+        self.rules.clone()
+    }
+
+    pub fn set(&mut self, rules: PriorityRules) -> std::io::Result<()> {
+        self.rules = rules;
+        match self.path {
+            Some(ref path) => storage::save(path.as_path(), &self.rules),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(text: &str) -> scheduler::Record {
+        scheduler::Record {
+            tweet_id: "1".to_string(),
+            author_id: "100".to_string(),
+            created_at: "".to_string(),
+            text: text.to_string(),
+            name: "name".to_string(),
+            username: "user".to_string(),
+            profile_image_url: "".to_string(),
+            attachments: vec![],
+            lang: "ja".to_string(),
+            is_retweet: false,
+            is_reply: false,
+            priority: false,
+        }
+    }
+
+    #[test]
+    fn ts_matches_authors() {
+        let by_id = PriorityRules {
+            authors: vec!["100".to_string()],
+            ..Default::default()
+        };
+        let by_name = PriorityRules {
+            authors: vec!["@User".to_string()],
+            ..Default::default()
+        };
+        let other = PriorityRules {
+            authors: vec!["200".to_string(), "@someone".to_string()],
+            ..Default::default()
+        };
+
+        assert!(by_id.matches(&record("text")));
+        assert!(by_name.matches(&record("text")));
+        assert!(!other.matches(&record("text")));
+    }
+
+    #[test]
+    fn ts_matches_keywords() {
+        let rules = PriorityRules {
+            keywords: vec!["".to_string(), "Release".to_string(), "速報".to_string()],
+            ..Default::default()
+        };
+
+        assert!(rules.matches(&record("new release today")));
+        assert!(rules.matches(&record("【速報】")));
+        // Empty keywords match nothing
+        assert!(!rules.matches(&record("こんにちは")));
+    }
+}
@@ -0,0 +1,470 @@
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::host;
+use crate::scheduler;
+use crate::storage;
+use crate::voicegen_agent;
+use crate::wav;
+
+pub const DIR_NAME: &str = "recordings";
+
+// Pause after each tweet so that they do not run together
+const GAP_MS: u64 = 600;
+// Sizes in WAV headers are 32 bit
+const DATA_BYTES_MAX: u64 = u32::MAX as u64 - wav::HEADER_LENGTH as u64;
+// End of the index, each chapter is written over it
+const INDEX_TAIL: &[u8] = b"]}";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum RecordMode {
+    Off,
+    // One file for the whole day
+    Rolling,
+    // A new episode every hour
+    Hourly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecordSettings {
+    pub mode: RecordMode,
+    // "recordings" in the app data directory when not given
+    pub dir: Option<PathBuf>,
+    // Minutes east of UTC, for file names and timestamps
+    pub utc_offset: i32,
+}
+
+impl RecordSettings {
+    pub fn new() -> Self {
+        RecordSettings {
+            mode: RecordMode::Off,
+            dir: None,
+            utc_offset: 0,
+        }
+    }
+}
+
+// A tweet as it was read
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub record: scheduler::Record,
+    pub speech: voicegen_agent::Speech,
+}
+
+#[derive(Debug, Clone)]
+pub enum RecorderControl {
+    Settings(RecordSettings),
+    Append(Entry),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Chapter {
+    // Seconds from the beginning of the episode
+    pub start: f64,
+    pub duration: f64,
+    pub recorded_at: String,
+    pub tweet_id: String,
+    pub author_id: String,
+    pub name: String,
+    pub username: String,
+    pub created_at: String,
+    pub text: String,
+}
+
+// Written next to the audio file with the same name.
+// Field order matters, the chapters are last so that they can be appended to.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Index {
+    pub audio: String,
+    pub chapters: Vec<Chapter>,
+}
+
+// Calendar fields of a unix time, Howard Hinnant's civil_from_days
+fn civil_time(unix_secs: i64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = unix_secs.div_euclid(86400);
+    let secs = unix_secs.rem_euclid(86400) as u32;
+
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn local_secs(now: std::time::SystemTime, utc_offset: i32) -> i64 {
+    let unix_secs = now
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    unix_secs + utc_offset as i64 * 60
+}
+
+// RFC 3339 in local time
+pub fn timestamp(now: std::time::SystemTime, utc_offset: i32) -> String {
+    let (y, mo, d, h, mi, s) = civil_time(local_secs(now, utc_offset));
+    let sign = if utc_offset < 0 { '-' } else { '+' };
+    let offset = utc_offset.abs();
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}{:02}:{:02}",
+        y, mo, d, h, mi, s, sign, offset / 60, offset % 60
+    )
+}
+
+fn episode_name(mode: RecordMode, now: std::time::SystemTime, utc_offset: i32) -> String {
+    let (y, mo, d, h, _, _) = civil_time(local_secs(now, utc_offset));
+
+    match mode {
+        RecordMode::Hourly => format!("{:04}-{:02}-{:02}_{:02}", y, mo, d, h),
+        _ => format!("{:04}-{:02}-{:02}", y, mo, d),
+    }
+}
+
+// Audio file kept valid after every append, so that it can be listened to while recording
+struct Episode {
+    name: String,
+    // Episodes too long for a single file continue in further parts
+    part: u32,
+    file: std::fs::File,
+    index_file: std::fs::File,
+    chapters: usize,
+    channels: u16,
+    sample_rate: u32,
+    data_len: u32,
+}
+
+impl Episode {
+    // Episodes of the same name are continued, in their own format
+    fn open(dir: &Path, name: &str, part: u32, channels: u16, sample_rate: u32) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let stem = if part == 0 {
+            name.to_string()
+        } else {
+            format!("{}_part{}", name, part + 1)
+        };
+        let audio = format!("{}.wav", stem);
+        let index_path = dir.join(format!("{}.json", stem));
+        let head = std::fs::File::open(dir.join(&audio)).ok().and_then(|mut f| {
+            let mut head = [0u8; wav::HEADER_LENGTH];
+            f.read_exact(&mut head).ok()?;
+            wav::parse_header(&head)
+        });
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(&audio))?;
+
+        let (mut index, channels, sample_rate, data_len) = match head {
+            Some((channels, sample_rate, data_len)) => (
+                storage::load::<Index>(index_path.as_path()).unwrap_or_default(),
+                channels,
+                sample_rate,
+                data_len,
+            ),
+            None => (Index::default(), channels, sample_rate, 0),
+        };
+        index.audio = audio;
+
+        // Written whole once, the chapters are appended afterwards
+        let json = serde_json::to_vec(&index)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let mut index_file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(index_path)?;
+        index_file.write_all(&json)?;
+
+        let mut episode = Episode {
+            name: name.to_string(),
+            part,
+            file,
+            index_file,
+            chapters: index.chapters.len(),
+            channels,
+            sample_rate,
+            data_len,
+        };
+        // Drops whatever an interrupted append left behind
+        episode.file.set_len(wav::HEADER_LENGTH as u64 + episode.data_len as u64)?;
+        episode.write_header()?;
+
+        Ok(episode)
+    }
+
+    fn has_room(&self, audio: &wav::Wav) -> bool {
+        self.data_len as u64 + audio.samples.len() as u64 * 2 <= DATA_BYTES_MAX
+    }
+
+    fn duration(&self) -> f64 {
+        self.data_len as f64 / (self.channels as u32 * 2 * self.sample_rate) as f64
+    }
+
+    fn write_header(&mut self) -> std::io::Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&wav::header(self.channels, self.sample_rate, self.data_len))
+    }
+
+    fn append(&mut self, audio: &wav::Wav, chapter: Chapter) -> std::io::Result<()> {
+        let data = audio.data();
+
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&data)?;
+        self.data_len += data.len() as u32;
+        self.write_header()?;
+        self.file.flush()?;
+
+        self.append_chapter(&chapter)
+    }
+
+    // Only the new chapter is written, the index stays valid JSON
+    fn append_chapter(&mut self, chapter: &Chapter) -> std::io::Result<()> {
+        let mut json = if self.chapters == 0 { vec![] } else { b",".to_vec() };
+        json.extend(
+            serde_json::to_vec(chapter)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+        );
+        json.extend(INDEX_TAIL);
+
+        self.index_file.seek(SeekFrom::End(-(INDEX_TAIL.len() as i64)))?;
+        self.index_file.write_all(&json)?;
+        self.chapters += 1;
+
+        Ok(())
+    }
+}
+
+// Name and text followed by the gap, in the format of the episode.
+// Returns the audio and the length of the speech in it.
+fn compose(parts: &[wav::Wav], channels: u16, sample_rate: u32) -> (wav::Wav, f64) {
+    let mut audio = wav::Wav {
+        channels,
+        sample_rate,
+        samples: vec![],
+    };
+    for part in parts.iter() {
+        audio.samples.extend(part.convert(channels, sample_rate).samples);
+    }
+    let duration = audio.duration();
+
+    let gap = wav::Wav::silence(channels, sample_rate, std::time::Duration::from_millis(GAP_MS));
+    audio.samples.extend(gap.samples);
+
+    (audio, duration)
+}
+
+pub struct Recorder {
+    default_dir: Option<PathBuf>,
+    settings: RecordSettings,
+    episode: Option<Episode>,
+}
+
+impl Recorder {
+    pub fn new(default_dir: Option<PathBuf>) -> Self {
+        Recorder {
+            default_dir,
+            settings: RecordSettings::new(),
+            episode: None,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: RecordSettings) {
+        if settings != self.settings {
+            self.episode = None;
+        }
+        self.settings = settings;
+    }
+
+    pub fn append(&mut self, entry: &Entry, now: std::time::SystemTime) -> std::io::Result<()> {
+        if self.settings.mode == RecordMode::Off {
+            return Ok(());
+        }
+
+        let dir = match self.settings.dir.clone().or_else(|| self.default_dir.clone()) {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+
+        let parts: Vec<wav::Wav> = [&entry.speech.name, &entry.speech.text]
+            .iter()
+            .filter(|data| !data.is_empty())
+            .filter_map(|data| wav::Wav::parse(data))
+            .collect();
+        let first = match parts.first() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+
+        let name = episode_name(self.settings.mode, now, self.settings.utc_offset);
+        if self.episode.as_ref().map_or(true, |e| e.name != name) {
+            println!("recorder: episode {:?}", name);
+            self.episode = Some(Episode::open(&dir, &name, 0, first.channels, first.sample_rate)?);
+        }
+
+        let (audio, duration) = loop {
+            let episode = self.episode.as_ref().unwrap();
+            let (audio, duration) = compose(&parts, episode.channels, episode.sample_rate);
+            if episode.has_room(&audio) {
+                break (audio, duration);
+            }
+
+            let part = episode.part + 1;
+            println!("recorder: episode {:?} part {:?}", name, part + 1);
+            self.episode = Some(Episode::open(&dir, &name, part, first.channels, first.sample_rate)?);
+        };
+        let episode = self.episode.as_mut().unwrap();
+
+        let record = &entry.record;
+        let chapter = Chapter {
+            start: episode.duration(),
+            duration,
+            recorded_at: timestamp(now, self.settings.utc_offset),
+            tweet_id: record.tweet_id.clone(),
+            author_id: record.author_id.clone(),
+            name: record.name.clone(),
+            username: record.username.clone(),
+            created_at: record.created_at.clone(),
+            text: record.text.clone(),
+        };
+
+        episode.append(&audio, chapter)
+    }
+}
+
+// Files are written on a thread of its own, away from the runtime
+pub fn start(
+    host: host::Host,
+    mut recorder_rx: tokio::sync::mpsc::Receiver<RecorderControl>,
+) {
+    std::thread::spawn(move || {
+        let mut recorder = Recorder::new(host.path(DIR_NAME));
+
+        loop {
+            match recorder_rx.blocking_recv() {
+                Some(RecorderControl::Settings(settings)) => {
+                    println!("recorder: {:?}", settings);
+                    recorder.set_settings(settings);
+                }
+
+                Some(RecorderControl::Append(entry)) => {
+                    if let Err(e) = recorder.append(&entry, std::time::SystemTime::now()) {
+                        println!("recorder: failed to record {:?} {:?}", entry.record.tweet_id, e);
+                    }
+                }
+
+                None => {
+                    println!("recorder: exit");
+                    return;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(twid: &str, samples: usize) -> Entry {
+        let voice = wav::Wav {
+            channels: 1,
+            sample_rate: 1000,
+            samples: vec![100; samples],
+        };
+
+        Entry {
+            record: scheduler::Record {
+                tweet_id: twid.to_string(),
+                author_id: "100".to_string(),
+                created_at: "".to_string(),
+                text: "text".to_string(),
+                name: "name".to_string(),
+                username: "user".to_string(),
+                profile_image_url: "".to_string(),
+                attachments: vec![],
+                lang: "ja".to_string(),
+                is_retweet: false,
+                is_reply: false,
+                priority: false,
+            },
+            speech: voicegen_agent::Speech {
+                tweet_id: twid.to_string(),
+                job_id: 0,
+                addr: std::net::SocketAddr::from(([127, 0, 0, 1], 50021)),
+                text: voice.to_bytes(),
+                name: vec![],
+                cacheable: true,
+            },
+        }
+    }
+
+    fn at(unix_secs: u64) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(unix_secs)
+    }
+
+    #[test]
+    fn ts_timestamp() {
+        // 2023-01-31 23:30:00 UTC
+        assert_eq!(timestamp(at(1675207800), 0), "2023-01-31T23:30:00+00:00");
+        assert_eq!(timestamp(at(1675207800), 540), "2023-02-01T08:30:00+09:00");
+        assert_eq!(episode_name(RecordMode::Hourly, at(1675207800), -90), "2023-01-31_22");
+    }
+
+    #[test]
+    fn ts_record_episodes() {
+        let dir = std::env::temp_dir().join(format!("twradio_recorder_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut recorder = Recorder::new(Some(dir.clone()));
+        recorder.append(&entry("1", 1000), at(1675207800)).unwrap();
+        assert!(!dir.exists());
+
+        recorder.set_settings(RecordSettings {
+            mode: RecordMode::Hourly,
+            dir: None,
+            utc_offset: 0,
+        });
+        recorder.append(&entry("1", 1000), at(1675207800)).unwrap();
+        recorder.append(&entry("2", 500), at(1675207900)).unwrap();
+        recorder.append(&entry("3", 500), at(1675209600)).unwrap();
+
+        let audio = wav::Wav::parse(&std::fs::read(dir.join("2023-01-31_23.wav")).unwrap()).unwrap();
+        let gap = GAP_MS as f64 / 1000.0;
+        assert!((audio.duration() - (1.5 + gap * 2.0)).abs() < 1e-9);
+
+        let index = storage::load::<Index>(dir.join("2023-01-31_23.json").as_path()).unwrap();
+        assert_eq!(index.audio, "2023-01-31_23.wav");
+        assert_eq!(index.chapters.len(), 2);
+        assert_eq!(index.chapters[1].tweet_id, "2");
+        assert!((index.chapters[1].start - (1.0 + gap)).abs() < 1e-9);
+
+        // A new hour starts a new episode
+        let index = storage::load::<Index>(dir.join("2023-02-01_00.json").as_path()).unwrap();
+        assert_eq!(index.chapters.len(), 1);
+
+        // Episodes are continued after a restart
+        let mut recorder = Recorder::new(Some(dir.clone()));
+        recorder.set_settings(RecordSettings {
+            mode: RecordMode::Hourly,
+            dir: None,
+            utc_offset: 0,
+        });
+        recorder.append(&entry("4", 500), at(1675209700)).unwrap();
+        let index = storage::load::<Index>(dir.join("2023-02-01_00.json").as_path()).unwrap();
+        assert_eq!(index.chapters.len(), 2);
+        assert!((index.chapters[1].start - (0.5 + gap)).abs() < 1e-9);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use crate::audio_cache;
+use crate::scheduler;
+use crate::voicegen_agent;
+
+pub const DIR_NAME: &str = "replay_cache";
+
+const CACHE_BYTES_MAX: u64 = 256 * 1024 * 1024;
+
+// Synthesized tweets kept on disk so that tweets read again do not need re-synthesis.
+pub struct ReplayCache {
+    cache: audio_cache::AudioCache,
+}
+
+impl ReplayCache {
+    pub fn load(dir: Option<PathBuf>) -> Self {
+        ReplayCache {
+            cache: audio_cache::AudioCache::load(dir, CACHE_BYTES_MAX),
+        }
+    }
+
+    // Tweet id and everything that changes how the tweet sounds
+    pub fn key(playbook: &voicegen_agent::Playbook) -> String {
+        let inputs = serde_json::json!([
+            // The text differs with a headline or a replaced body
+            playbook.text,
+            playbook.addr,
+            playbook.speaker,
+            playbook.speech_rate,
+            playbook.name,
+            playbook.emoji.mode,
+            // HashMap order differs between runs
+            playbook.emoji.overrides.iter().collect::<std::collections::BTreeMap<_, _>>(),
+            playbook.lang,
+            playbook.translation,
+        ]);
+
+        format!(
+            "{}/{:016x}",
+            playbook.tweet_id,
+            scheduler::stable_hash(inputs.to_string().as_str())
+        )
+    }
+
+    pub fn get(&mut self, key: &str, playbook: &voicegen_agent::Playbook) -> Option<voicegen_agent::Speech> {
+        let name = self.cache.get(format!("{}/name", key).as_str())?;
+        let text = self.cache.get(format!("{}/text", key).as_str())?;
+
+        Some(voicegen_agent::Speech {
+            tweet_id: playbook.tweet_id.clone(),
+            job_id: 0,
+            addr: playbook.addr,
+            text,
+            name,
+            cacheable: true,
+        })
+    }
+
+    pub fn insert(&mut self, key: &str, speech: &voicegen_agent::Speech) {
+        self.cache.insert(format!("{}/name", key), &speech.name);
+        self.cache.insert(format!("{}/text", key), &speech.text);
+    }
+
+    // Every voice of the tweet
+    pub fn remove(&mut self, tweet_id: &str) {
+        let prefix = format!("{}/", tweet_id);
+        self.cache.remove_if(|key| key.starts_with(prefix.as_str()));
+    }
+
+    pub fn clear(&mut self) {
+        println!("replay_cache: clear");
+        self.cache.clear();
+    }
+}
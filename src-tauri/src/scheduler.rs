@@ -1,16 +1,32 @@
 use serde::{Deserialize, Serialize};
-use std::collections::LinkedList;
+use std::collections::HashMap;
 
 use crate::audio_player;
+use crate::author_rules;
+use crate::cancel_token;
+use crate::catch_up;
 use crate::display_bridge;
+use crate::host;
+use crate::language;
+use crate::mute_rules;
+use crate::priority_rules;
+use crate::recorder;
+use crate::replay_cache;
+use crate::translator;
+use crate::tweet_queue;
 use crate::twitter_data;
 use crate::twitter_agent;
 use crate::user_input;
 use crate::voicegen_agent;
+use crate::voicegen_filter;
+use crate::voicegen_observer;
 
 const HISTORY_LENGTH: usize = 128;
 const WAIT_LIST_MAX: usize = 64;
 const READY_LIST_MAX: usize = 64;
+const TTS_JOBS_MAX: usize = 4;
+// Tweet id prefix of announcements made by the scheduler itself
+const ANNOUNCEMENT_PREFIX: &str = "announcement-";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
@@ -22,6 +38,10 @@ pub struct Record {
     pub username: String,
     pub profile_image_url: String,
     pub attachments: Vec<(String, String)>,
+    pub lang: String,
+    pub is_retweet: bool,
+    pub is_reply: bool,
+    pub priority: bool,
 }
 
 impl Record {
@@ -50,6 +70,12 @@ impl Record {
             })
             .collect();
 
+        let referenced_type = |t: &str| {
+            tweet["referenced_tweets"]
+                .as_array()
+                .map_or(false, |refs| refs.iter().any(|r| r["type"] == t))
+        };
+
         Some ( Self {
             tweet_id: tweet["id"].as_str()?.to_string(),
             author_id: tweet["author_id"].as_str()?.to_string(),
@@ -59,25 +85,47 @@ impl Record {
             username: user["username"].as_str()?.to_string(),
             profile_image_url: user["profile_image_url"].as_str()?.to_string(),
             attachments,
+            lang: tweet["lang"].as_str().unwrap_or("").to_string(),
+            is_retweet: referenced_type("retweeted"),
+            is_reply: referenced_type("replied_to"),
+            priority: false,
         })
     }
 }
 
-fn remove<T>(list: &mut LinkedList<T>, index: usize) -> T {
-    match index {
-        0 => {
-            list.pop_front().unwrap()
-        }
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AutoVoice {
+    pub enabled: bool,
+    // Speakers to choose from. Every available speaker is used when empty.
+    pub pool: Vec<voicegen_observer::Speaker>,
+}
 
-        _ => {
-            // split_off function should compute in O(n) time.
-            let mut split = list.split_off(index);
-            let v = split.pop_front().unwrap();
-            list.append(&mut split);
+// FNV-1a, stable across builds and sessions unlike std's hasher
+pub fn stable_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
 
-            return v;
-        }
+fn assign_voice<'a>(
+    author_id: &str,
+    available: &'a [voicegen_observer::Speaker],
+    pool: &[voicegen_observer::Speaker],
+) -> Option<&'a voicegen_observer::Speaker> {
+    let mut candidates: Vec<&voicegen_observer::Speaker> = available
+        .iter()
+        .filter(|s| pool.is_empty() || pool.contains(s))
+        .collect();
+    if candidates.is_empty() {
+        return None;
     }
+
+    candidates.sort_by(|a, b| {
+        (&a.engine, a.speaker, &a.name, &a.style).cmp(&(&b.engine, b.speaker, &b.name, &b.style))
+    });
+
+    let index = stable_hash(author_id) % candidates.len() as u64;
+    Some(candidates[index as usize])
 }
 
 struct Settings {
@@ -85,6 +133,17 @@ struct Settings {
     pub speaker: u64,
     pub speech_rate: f64,
     pub paused: bool,
+    pub emoji: voicegen_filter::EmojiSettings,
+    pub author_rules: author_rules::RuleMap,
+    pub auto_voice: AutoVoice,
+    pub speakers: Vec<voicegen_observer::Speaker>,
+    pub mute: mute_rules::MuteFilter,
+    pub priority: priority_rules::PriorityRules,
+    // language -> speaker
+    pub language_voices: HashMap<String, voicegen_observer::Speaker>,
+    pub translation: translator::TranslateSettings,
+    pub catch_up: catch_up::CatchUpSettings,
+    pub record: recorder::RecordSettings,
 }
 
 impl Settings {
@@ -94,25 +153,195 @@ impl Settings {
             speaker: 0,
             speech_rate: 1.0f64,
             paused: false,
+            emoji: voicegen_filter::EmojiSettings::new(),
+            author_rules: author_rules::RuleMap::new(),
+            auto_voice: AutoVoice {
+                enabled: false,
+                pool: vec![],
+            },
+            speakers: vec![],
+            mute: mute_rules::MuteFilter::new(mute_rules::MuteRules::default()),
+            priority: priority_rules::PriorityRules::default(),
+            language_voices: HashMap::new(),
+            translation: translator::TranslateSettings::new(),
+            catch_up: catch_up::CatchUpSettings::new(),
+            record: recorder::RecordSettings::new(),
         }
     }
+
+    // Voice reading the author in the language given
+    fn voice(&self, lang: &str, author_id: &str) -> (std::net::SocketAddr, u64) {
+        if let Some(s) = self
+            .author_rules
+            .get(author_id)
+            .and_then(|rule| rule.speaker.as_ref())
+        {
+            return (s.addr, s.speaker);
+        }
+
+        if let Some(s) = self.language_voices.get(lang) {
+            return (s.addr, s.speaker);
+        }
+
+        if self.auto_voice.enabled {
+            if let Some(s) = assign_voice(author_id, &self.speakers, &self.auto_voice.pool) {
+                return (s.addr, s.speaker);
+            }
+        }
+
+        (self.addr, self.speaker)
+    }
+
+    // backlog is the number of queued tweets, used for catching up
+    pub fn playbook(&self, record: Record, backlog: usize) -> voicegen_agent::Playbook {
+        let mut speech_rate = self.speech_rate;
+        let mut record = record;
+        let mut emoji = self.emoji.clone();
+
+        if self.catch_up.is_headlines(backlog) {
+            record.text = catch_up::headline(record.text.as_str());
+        }
+
+        let mut lang = match language::resolve(&record).as_str() {
+            "und" => language::JAPANESE.to_string(),
+            lang => lang.to_string(),
+        };
+
+        // Foreign tweets are read in the target language after translation
+        let mut translation = None;
+        if self.translation.enabled && lang != self.translation.target {
+            let (source_addr, source_speaker) = self.voice(lang.as_str(), &record.author_id);
+            translation = Some(translator::Job {
+                source: lang.clone(),
+                target: self.translation.target.clone(),
+                url: self.translation.url.clone(),
+                api_key: self.translation.api_key.clone(),
+                source_addr,
+                source_speaker,
+            });
+            lang = self.translation.target.clone();
+        }
+
+        let (addr, speaker) = self.voice(lang.as_str(), &record.author_id);
+
+        // Emoji readings are Japanese
+        if lang != language::JAPANESE {
+            emoji.mode = voicegen_filter::EmojiMode::Drop;
+        }
+
+        if let Some(rule) = self.author_rules.get(&record.author_id) {
+            if let Some(rate) = rule.speech_rate {
+                speech_rate = rate;
+            }
+
+            if let Some(ref nickname) = rule.nickname {
+                record.name = nickname.clone();
+            }
+
+            if !rule.read_name {
+                record.name = "".to_string();
+            }
+        }
+
+        let speech_rate = self.catch_up.speech_rate(speech_rate, backlog);
+
+        let mut playbook = voicegen_agent::into(record, addr, speaker, speech_rate, emoji, lang);
+        playbook.translation = translation;
+
+        playbook
+    }
+}
+
+// Synthesis jobs sent to voicegen_agent, identified by job id.
+// Canceled jobs abort their requests; results of unknown jobs are discarded when they arrive.
+#[derive(Debug)]
+struct TTSJob {
+    job_id: u64,
+    tweet_id: String,
+    cache_key: String,
+    cancel: cancel_token::CancelToken,
+}
+
+#[derive(Debug)]
+struct TTSJobs {
+    next_id: u64,
+    in_flight: Vec<TTSJob>,
 }
 
-#[derive(Debug, PartialEq)]
-enum TTSState {
-    Waiting,
-    Processing,
-    Canceling,
+impl TTSJobs {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            in_flight: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn can_dispatch(&self) -> bool {
+        self.in_flight.len() < TTS_JOBS_MAX
+    }
+
+    pub fn is_processing(&self, twid: &str) -> bool {
+        self.in_flight.iter().any(|j| j.tweet_id == twid)
+    }
+
+    pub fn start(&mut self, playbook: &mut voicegen_agent::Playbook, cache_key: String) {
+        self.next_id += 1;
+        playbook.job_id = self.next_id;
+
+        self.in_flight.push(TTSJob {
+            job_id: playbook.job_id,
+            tweet_id: playbook.tweet_id.clone(),
+            cache_key,
+            cancel: playbook.cancel.clone(),
+        });
+    }
+
+    pub fn cancel(&mut self) {
+        println!("scheduler: cancelling {:?} jobs", self.in_flight.len());
+        for job in self.in_flight.drain(..) {
+            job.cancel.cancel();
+        }
+    }
+
+    // Cancel jobs for tweets which are no longer needed
+    pub fn retain<F: Fn(&str) -> bool>(&mut self, keep: F) {
+        let (kept, dropped): (Vec<TTSJob>, Vec<TTSJob>) =
+            self.in_flight.drain(..).partition(|j| keep(&j.tweet_id));
+
+        for job in dropped {
+            println!("scheduler: cancelling job {:?}", job.job_id);
+            job.cancel.cancel();
+        }
+        self.in_flight = kept;
+    }
+
+    // Returns the result with its replay cache key unless the job is unknown
+    pub fn finish(&mut self, result: voicegen_agent::TTSResult) -> Option<(voicegen_agent::TTSResult, String)> {
+        let job_id = match result {
+            Ok(ref s) => s.job_id,
+            Err(ref f) => f.job_id,
+        };
+
+        let index = self.in_flight.iter().position(|j| j.job_id == job_id)?;
+        let job = self.in_flight.remove(index);
+
+        Some((result, job.cache_key))
+    }
 }
 
 
+fn is_announcement(twid: &str) -> bool {
+    twid.starts_with(ANNOUNCEMENT_PREFIX)
+}
+
 struct Context {
     pub name: String,
     pub forcus_id: Option<String>,
-    pub wait_list: LinkedList<Record>,
-    pub ready_list: LinkedList<Record>,
-    pub played_list: LinkedList<Record>,
-    pub speech_cache: LinkedList<voicegen_agent::Speech>,
+    pub queue: tweet_queue::TweetQueue,
 }
 
 impl Context {
@@ -120,419 +349,1428 @@ impl Context {
         Self {
             name,
             forcus_id: None,
-            wait_list: LinkedList::<Record>::new(),
-            ready_list: LinkedList::<Record>::new(),
-            played_list: LinkedList::<Record>::new(),
-            speech_cache: LinkedList::<voicegen_agent::Speech>::new(),
+            queue: tweet_queue::TweetQueue::new(),
         }
     }
 
     pub fn add_new_tweet(&mut self, msg: &Record) {
-        self.wait_list.push_back(msg.clone());
+        if !msg.priority {
+            self.queue.push_back(msg.clone());
+            return;
+        }
+
+        // Priority tweets are queued after the preceding priority tweets
+        self.queue.insert_after_leading(msg.clone(), |r| r.priority);
+    }
+
+    // Muted tweets are never read; they go straight into the history
+    pub fn add_muted_tweet(&mut self, msg: &Record) -> Vec<String> {
+        self.queue.push_played(msg.clone());
+
+        self.trim_history()
     }
 
-    pub fn fetch_for_tts(&mut self) -> Record {
-        self.wait_list.front().unwrap().clone()
+    // Queued and not synthesized yet
+    pub fn waiting_len(&self) -> usize {
+        self.queue.count(tweet_queue::State::Waiting) + self.queue.count(tweet_queue::State::Synthesizing)
     }
 
-    pub fn add_tss_result(&mut self, tts_result: Option<voicegen_agent::Speech>) {
+    pub fn ready_len(&self) -> usize {
+        self.queue.count(tweet_queue::State::Ready)
+    }
+
+    pub fn queued_len(&self) -> usize {
+        self.waiting_len() + self.ready_len()
+    }
+
+    fn is_queued(&self, twid: &str) -> bool {
+        matches!(self.queue.state(twid), Some(s) if s != tweet_queue::State::Played)
+    }
+
+    fn is_played(&self, twid: &str) -> bool {
+        self.queue.state(twid) == Some(tweet_queue::State::Played)
+    }
+
+    // First waiting tweet, marked as being synthesized
+    fn fetch_for_tts(&mut self) -> Option<Record> {
+        let record = self
+            .queue
+            .queued()
+            .find(|x| x.state == tweet_queue::State::Waiting)?
+            .record
+            .clone();
+
+        self.queue.set_state(&record.tweet_id, tweet_queue::State::Synthesizing);
+        Some(record)
+    }
+
+    pub fn add_tss_result(&mut self, tts_result: voicegen_agent::TTSResult) {
         match tts_result {
-            Some(tts_result) => {
-                println!("Text to speech is complete {:?}", tts_result.tweet_id);
+            Ok(speech) => {
+                println!("Text to speech is complete {:?}", speech.tweet_id);
 
-                self.ready_list.push_back(self.wait_list.pop_front().unwrap());
-                self.speech_cache.push_back(tts_result);
+                let twid = speech.tweet_id.clone();
+                if !self.queue.set_speech(speech) {
+                    println!("Text to speech result is dropped {:?}", twid);
+                }
             }
-            None => {
-                println!("Text to speech is failed ");
+            Err(failed) => {
+                println!("Text to speech is failed {:?}", failed.tweet_id);
+
+                if self.queue.state(&failed.tweet_id) == Some(tweet_queue::State::Synthesizing) {
+                    self.queue.set_state(&failed.tweet_id, tweet_queue::State::Waiting);
+                }
             }
         }
     }
 
-    pub fn is_speech_ready(&self) -> bool {
-        self.ready_list.len() > 0
-    }
+    // Queue the history again from twid.
+    // Tweets rejected by keep (e.g. muted ones) stay in the history.
+    pub fn rewind_to_twid<F: Fn(&Record) -> bool>(&mut self, twid: &str, keep: F) -> bool {
+        if !self.is_played(twid) {
+            return false;
+        }
 
-    pub fn fetch_for_playback(&mut self) -> (voicegen_agent::Speech, Option<String>){
-        let mut overflow_id: Option<String> = None;
-        let target_tw = self.ready_list.pop_front().unwrap();
+        let skipped: Vec<String> = self
+            .queue
+            .played()
+            .skip_while(|x| x.record.tweet_id != twid)
+            .filter(|x| x.record.tweet_id != twid && !keep(&x.record))
+            .map(|x| x.record.tweet_id.clone())
+            .collect();
+        let skipped: Vec<Record> = skipped
+            .iter()
+            .filter_map(|id| self.queue.take(id))
+            .map(|x| x.record)
+            .collect();
 
-        let index = self.speech_cache.iter().position(|x| x.tweet_id == target_tw.tweet_id).unwrap();
-        let speech = remove(&mut self.speech_cache, index);
+        self.queue.rewind_to(twid);
 
-        self.played_list.push_back(target_tw);
-        if self.played_list.len() > HISTORY_LENGTH {
-            let ve = self.played_list.pop_front().unwrap();
-            overflow_id = Some(ve.tweet_id);
+        for r in skipped {
+            self.queue.push_played(r);
         }
 
-        return (speech, overflow_id)
+        true
     }
 
-    pub fn jump_to_twid(&mut self, twid: &String) -> Vec<String> {
-        let p = self.wait_list.iter().position(|x| x.tweet_id == *twid);
-        if p.is_some() {
-            self.played_list.append(&mut self.ready_list);
+    // Read a single tweet of the history again before the rest of the queue
+    pub fn replay_twid(&mut self, twid: &str) -> bool {
+        if !self.is_played(twid) {
+            return false;
+        }
+
+        self.queue.move_to_front(twid)
+    }
+
+    // The last played tweet is the one being read now.
+    // Tweets rejected by keep (e.g. muted ones) are passed over.
+    pub fn previous_twid<F: Fn(&Record) -> bool>(&self, keep: F) -> Option<String> {
+        let mut played = self.queue.played().collect::<Vec<_>>();
+        let last = played.pop()?;
 
-            let tail = self.wait_list.split_off(p.unwrap());
+        let previous = played.into_iter().rev().find(|x| keep(&x.record));
+        Some(previous.unwrap_or(last).record.tweet_id.clone())
+    }
 
-            self.played_list.append(&mut self.wait_list);
-            self.wait_list = tail;
+    pub fn last_twid(&self) -> Option<String> {
+        self.queue.last_played().map(|x| x.record.tweet_id.clone())
+    }
 
-            self.speech_cache.clear();
+    fn trim_history(&mut self) -> Vec<String> {
+        self.queue.trim_history(HISTORY_LENGTH)
+    }
+
+    // Move the next count queued tweets into the history without reading them.
+    // Returns tweets dropped from the history.
+    pub fn skip(&mut self, count: usize) -> Vec<String> {
+        if count == 0 {
+            return vec![];
         }
 
-        let p = self.ready_list.iter().position(|x| x.tweet_id == *twid);
-        if p.is_some() {
-            let tail = self.ready_list.split_off(p.unwrap());
+        let last = self
+            .queue
+            .queued()
+            .take(count)
+            .last()
+            .map(|x| x.record.tweet_id.clone());
 
-            self.played_list.append(&mut self.ready_list);
+        if let Some(twid) = last {
+            self.queue.play_through(&twid);
+        }
 
-            self.ready_list = tail;
+        self.trim_history()
+    }
 
-            let tail = self.speech_cache.split_off(p.unwrap());
-            self.speech_cache = tail;
+    pub fn remove_twid(&mut self, twid: &str) -> bool {
+        if !self.is_queued(twid) {
+            return false;
         }
 
-        let mut drop_list : Vec<String> = vec![];
-        while self.played_list.len() >= HISTORY_LENGTH {
-            let ve = self.played_list.pop_front().unwrap();
-            drop_list.push(ve.tweet_id);
+        self.queue.take(twid).is_some()
+    }
+
+    pub fn move_to_front(&mut self, twid: &str) -> bool {
+        if !self.is_queued(twid) {
+            return false;
         }
 
-        drop_list
+        self.queue.move_to_front(twid)
+    }
+
+    // Skip the oldest count queued tweets and announce it instead.
+    // Priority tweets are kept queued ahead of the announcement.
+    // Returns tweets dropped from the history.
+    pub fn skip_ahead(&mut self, count: usize) -> Vec<String> {
+        let skipped: Vec<Record> = self
+            .queue
+            .queued()
+            .filter(|x| !x.record.priority)
+            .take(count)
+            .map(|x| x.record.clone())
+            .collect();
+        let (first, last) = match (skipped.first(), skipped.last()) {
+            (Some(first), Some(last)) => (first.clone(), last.tweet_id.clone()),
+            _ => return vec![],
+        };
+
+        let kept: Vec<String> = self
+            .queue
+            .queued()
+            .take_while(|x| x.record.tweet_id != last)
+            .filter(|x| x.record.priority)
+            .map(|x| x.record.tweet_id.clone())
+            .collect();
+        let kept: Vec<tweet_queue::Item> = kept
+            .iter()
+            .filter_map(|twid| self.queue.take(twid))
+            .collect();
+
+        let announcement = Record {
+            tweet_id: format!("{}{}-{}", ANNOUNCEMENT_PREFIX, first.tweet_id, last),
+            author_id: "".to_string(),
+            text: catch_up::announcement(skipped.len(), first.name.as_str()),
+            name: "".to_string(),
+            attachments: vec![],
+            lang: language::JAPANESE.to_string(),
+            is_retweet: false,
+            is_reply: false,
+            priority: false,
+            ..first
+        };
+
+        self.queue.play_through(&last);
+        self.queue.push_front(announcement);
+        for item in kept.into_iter().rev() {
+            self.queue.put_front(item);
+        }
+
+        self.trim_history()
+    }
+
+    // Drop every queued tweet, the history is kept
+    pub fn clear_queue(&mut self) -> Vec<String> {
+        self.queue.clear_queued()
+    }
+
+    pub fn is_speech_ready(&self) -> bool {
+        matches!(self.queue.first_queued(), Some(x) if x.state == tweet_queue::State::Ready)
+    }
+
+    pub fn fetch_for_playback(&mut self) -> (Record, voicegen_agent::Speech, Option<String>){
+        let target = self.queue.first_queued().unwrap();
+        let record = target.record.clone();
+        let speech = target.speech.clone().unwrap();
+
+        self.queue.play_through(&record.tweet_id);
+        let overflow_id = self.trim_history().pop();
+
+        (record, speech, overflow_id)
+    }
+
+    pub fn jump_to_twid(&mut self, twid: &String) -> Vec<String> {
+        let prev = self
+            .queue
+            .queued()
+            .take_while(|x| x.record.tweet_id != *twid)
+            .last()
+            .map(|x| x.record.tweet_id.clone());
+
+        if self.is_queued(twid) {
+            if let Some(prev) = prev {
+                self.queue.play_through(&prev);
+            }
+        }
+
+        self.trim_history()
     }
 
     pub fn drop_all(&mut self) -> Vec<String> {
-        let mut drop_list : Vec<String> = vec![];
+        self.queue.clear()
+    }
+
+    pub fn remove_cache(&mut self) {
+        self.queue.reset_ready();
+    }
 
-        self.speech_cache.split_off(0);
+}
 
-        while self.wait_list.len() > 0 {
-            let ve = self.wait_list.pop_front().unwrap();
-            drop_list.push(ve.tweet_id);
+// Events the scheduler reacts to
+#[derive(Debug)]
+enum Input {
+    Tweet(twitter_agent::Timeline, Record),
+    Speech(voicegen_agent::TTSResult),
+    AudioReady,
+    // The player skipped the speech and is ready
+    AudioFailed(audio_player::AudioError),
+    Speakers(Vec<voicegen_observer::Speaker>),
+    User(user_input::UserInput),
+}
+
+// Requests to the display bridge, voicegen_agent, the audio player and the recorder
+#[derive(Debug)]
+enum Output {
+    Display(display_bridge::DisplayContrl),
+    Synthesize(voicegen_agent::Playbook),
+    Audio(audio_player::AudioControl),
+    Record(recorder::RecorderControl),
+}
+
+fn add_tweet(
+    ctx: &mut Context,
+    settings: &Settings,
+    outputs: &mut Vec<Output>,
+    msg: Record,
+) {
+    match settings.mute.check(&msg) {
+        None => {
+            let mut msg = msg;
+            msg.priority = settings.priority.matches(&msg);
+
+            outputs.push(Output::Display(display_bridge::DisplayContrl::Add(ctx.name.clone(), msg.clone().into())));
+            ctx.add_new_tweet(&msg);
         }
 
-        while self.ready_list.len() > 0 {
-            let ve = self.ready_list.pop_front().unwrap();
-            drop_list.push(ve.tweet_id);
+        Some(reason) => {
+            println!("scheduler: muted {:?} {:?}", msg.tweet_id, reason);
+            if !settings.mute.show_muted() {
+                return;
+            }
+
+            let mut ve: display_bridge::ViewElements = msg.clone().into();
+            ve.muted = true;
+            outputs.push(Output::Display(display_bridge::DisplayContrl::Add(ctx.name.clone(), ve)));
+
+            for id in ctx.add_muted_tweet(&msg) {
+                outputs.push(Output::Display(display_bridge::DisplayContrl::Delete(ctx.name.clone(), id)));
+            }
         }
+    }
+}
+
+// Scheduling state of both timelines, kept apart from the channels.
+// Every input is answered with the outputs to forward to the other agents.
+struct Scheduler {
+    current_tl_view: twitter_agent::Timeline,
+    current_search_tl: twitter_agent::Timeline,
+    ctx_user: Context,
+    ctx_search: Context,
+    tts_jobs: TTSJobs,
+    replay: replay_cache::ReplayCache,
+    settings: Settings,
+    // The player tells when it can take the next speech
+    audio_ready: bool,
+    // Tweet last handed to the player
+    playing_id: Option<String>,
+    outputs: Vec<Output>,
+}
 
-        while self.played_list.len() > 0 {
-            let ve = self.played_list.pop_front().unwrap();
-            drop_list.push(ve.tweet_id);
+impl Scheduler {
+    pub fn new(replay: replay_cache::ReplayCache) -> Self {
+        Self {
+            current_tl_view: twitter_agent::Timeline::User,
+            current_search_tl: twitter_agent::Timeline::Search{query: "".to_string()},
+            ctx_user: Context::new("user".to_string()),
+            ctx_search: Context::new("search".to_string()),
+            tts_jobs: TTSJobs::new(),
+            replay,
+            settings: Settings::new(),
+            audio_ready: false,
+            playing_id: None,
+            outputs: vec![],
         }
+    }
 
-        drop_list
+    // Tweets are left in the channels while the wait list is full
+    pub fn accepts(&self, timeline: &twitter_agent::Timeline) -> bool {
+        let ctx = match timeline {
+            twitter_agent::Timeline::User => &self.ctx_user,
+            twitter_agent::Timeline::Search {..} => &self.ctx_search,
+        };
+
+        !self.settings.paused && ctx.waiting_len() < WAIT_LIST_MAX
     }
 
-    pub fn remove_cache(&mut self) {
-        if self.ready_list.len() > 0 {
-            self.ready_list.append(&mut self.wait_list);
-            self.wait_list = self.ready_list.split_off(0);
-            self.speech_cache.split_off(0);
+    pub fn print_status(&self) {
+        let ctx = match self.current_tl_view {
+            twitter_agent::Timeline::User => &self.ctx_user,
+            twitter_agent::Timeline::Search {..} => &self.ctx_search,
+        };
+
+        println!("");
+        println!(
+            "current_search_tl: {:?}",
+            self.current_search_tl
+        );
+        println!(
+            "current_tl_view: {:?}",
+            self.current_tl_view
+        );
+        println!(
+            "tts_jobs: {:?}",
+            self.tts_jobs,
+        );
+        println!(
+            "setting: {:?}, {:?}, {:?}, {:?}",
+            self.settings.addr,
+            self.settings.speaker,
+            self.settings.speech_rate,
+            self.settings.paused,
+        );
+        println!(
+            "ctx: {:?}, {:?}, {:?}, {:?}, {:?}, {:?}, {:?}",
+            ctx.name,
+            ctx.forcus_id,
+            ctx.waiting_len(),
+            ctx.ready_len(),
+            ctx.queue.count(tweet_queue::State::Played),
+            self.tts_jobs.len(),
+            self.audio_ready,
+        );
+    }
+
+    pub fn handle(&mut self, input: Input) -> Vec<Output> {
+        match input {
+            Input::Tweet(timeline, msg) => self.on_tweet(timeline, msg),
+            Input::Speech(result) => self.on_speech(result),
+            Input::AudioReady => {
+                println!("scheduler: audio player is ready");
+                self.audio_ready = true;
+            }
+            Input::AudioFailed(error) => {
+                println!("scheduler: audio player skipped {:?} {:?}", self.playing_id, error);
+                // Broken speech would fail again when read from the cache
+                if let (audio_player::AudioError::Decode(_), Some(twid)) = (&error, &self.playing_id) {
+                    self.replay.remove(twid);
+                }
+                self.audio_ready = true;
+            }
+            Input::Speakers(speakers) => self.on_speakers(speakers),
+            Input::User(user) => self.on_user_input(user),
         }
+
+        self.proceed();
+
+        std::mem::take(&mut self.outputs)
     }
 
-}
+    fn on_tweet(&mut self, timeline: twitter_agent::Timeline, msg: Record) {
+        match timeline {
+            twitter_agent::Timeline::User => {
+                println!("user tl New tweet incoming {:?}", msg.tweet_id);
+
+                add_tweet(&mut self.ctx_user, &self.settings, &mut self.outputs, msg);
+            }
+
+            twitter_agent::Timeline::Search {..} => {
+                println!("search tl New tweet incoming {:?}", msg.tweet_id);
+                let ctx = &mut self.ctx_search;
+
+                if timeline != self.current_search_tl {
+                    println!("scheduler: drop all");
+                    self.outputs.push(Output::Audio(audio_player::AudioControl::Stop));
+                    ctx.forcus_id = None;
+
+                    for id in ctx.drop_all() {
+                        self.outputs.push(Output::Display(display_bridge::DisplayContrl::Delete(ctx.name.clone(), id)));
+                    }
+                    self.tts_jobs.cancel();
+
+                    self.current_search_tl = timeline;
+                }
+
+                add_tweet(ctx, &self.settings, &mut self.outputs, msg);
+            }
+        }
+    }
+
+    fn on_speech(&mut self, result: voicegen_agent::TTSResult) {
+        let ctx = match self.current_tl_view {
+            twitter_agent::Timeline::User => &mut self.ctx_user,
+            twitter_agent::Timeline::Search {..} => &mut self.ctx_search,
+        };
+
+        match self.tts_jobs.finish(result) {
+            Some((result, cache_key)) => {
+                match result {
+                    // Read untranslated, the translation is tried again next time
+                    Ok(ref speech) if speech.cacheable => self.replay.insert(cache_key.as_str(), speech),
+                    _ => {}
+                }
+                ctx.add_tss_result(result)
+            }
+            None => println!("tts result is ignored"),
+        }
+    }
+
+    fn on_speakers(&mut self, speakers: Vec<voicegen_observer::Speaker>) {
+        println!("scheduler: speakers updated {:?}", speakers.len());
+        self.settings.speakers = speakers;
+
+        if self.settings.auto_voice.enabled {
+            self.ctx_user.remove_cache();
+            self.ctx_search.remove_cache();
+            self.tts_jobs.cancel();
+        }
+    }
+
+    fn on_user_input(&mut self, user: user_input::UserInput) {
+        let mut ctx = match self.current_tl_view {
+            twitter_agent::Timeline::User => &mut self.ctx_user,
+            twitter_agent::Timeline::Search {..} => &mut self.ctx_search,
+        };
+
+        print!("User input - ");
+        match user {
+            user_input::UserInput::Jump(twid) => {
+                print!("jump to {:?}", twid);
+                self.outputs.push(Output::Audio(audio_player::AudioControl::Stop));
+
+                // An empty id cancels current playing speech only
+                if ctx.is_played(&twid) {
+                    ctx.rewind_to_twid(&twid, |r| !is_announcement(&r.tweet_id) && self.settings.mute.check(r).is_none());
+                    ctx.forcus_id = Some(twid);
+                    self.outputs.push(Output::Display(display_bridge::DisplayContrl::Scroll(ctx.name.clone(), ctx.forcus_id.as_ref().unwrap().clone())));
+                } else if !twid.is_empty() {
+                    let drop_list = ctx.jump_to_twid(&twid);
+                    self.tts_jobs.retain(|id| ctx.is_queued(id));
+
+                    for id in drop_list {
+                        self.outputs.push(Output::Display(display_bridge::DisplayContrl::Delete(ctx.name.clone(), id)));
+                    }
+
+                    ctx.forcus_id = Some(twid);
+                    self.outputs.push(Output::Display(display_bridge::DisplayContrl::Scroll(ctx.name.clone(), ctx.forcus_id.as_ref().unwrap().clone())));
+                }
+            },
+
+            user_input::UserInput::Replay(twid) => {
+                print!("replay {:?}", twid);
+
+                // Unknown tweets leave the one being read alone
+                if ctx.replay_twid(&twid) {
+                    self.outputs.push(Output::Audio(audio_player::AudioControl::Stop));
+                }
+            }
+
+            user_input::UserInput::RepeatLast => {
+                print!("repeat last");
+
+                if ctx.last_twid().map_or(false, |twid| ctx.replay_twid(&twid)) {
+                    self.outputs.push(Output::Audio(audio_player::AudioControl::Stop));
+                }
+            }
+
+            user_input::UserInput::Previous => {
+                print!("previous");
+                self.outputs.push(Output::Audio(audio_player::AudioControl::Stop));
+
+                let keep = |r: &Record| !is_announcement(&r.tweet_id) && self.settings.mute.check(r).is_none();
+                if let Some(twid) = ctx.previous_twid(keep) {
+                    ctx.rewind_to_twid(&twid, keep);
+                    ctx.forcus_id = Some(twid);
+                    self.outputs.push(Output::Display(display_bridge::DisplayContrl::Scroll(ctx.name.clone(), ctx.forcus_id.as_ref().unwrap().clone())));
+                }
+            }
+
+            user_input::UserInput::Next => {
+                print!("next");
+                // The following tweet starts once the player is ready again
+                self.outputs.push(Output::Audio(audio_player::AudioControl::Stop));
+            }
+
+            user_input::UserInput::Skip(count) => {
+                print!("skip {:?}", count);
+                self.outputs.push(Output::Audio(audio_player::AudioControl::Stop));
+
+                // The current tweet is already in the history
+                for id in ctx.skip(count.saturating_sub(1)) {
+                    self.outputs.push(Output::Display(display_bridge::DisplayContrl::Delete(ctx.name.clone(), id)));
+                }
+                self.tts_jobs.retain(|id| ctx.is_queued(id));
+            }
+
+            user_input::UserInput::Remove(twid) => {
+                print!("remove {:?}", twid);
+
+                if ctx.remove_twid(&twid) {
+                    self.outputs.push(Output::Display(display_bridge::DisplayContrl::Delete(ctx.name.clone(), twid)));
+                    self.tts_jobs.retain(|id| ctx.is_queued(id));
+                }
+            }
+
+            user_input::UserInput::MoveToFront(twid) => {
+                print!("move to front {:?}", twid);
+
+                if ctx.move_to_front(&twid) {
+                    let after = ctx.last_twid();
+                    self.outputs.push(Output::Display(display_bridge::DisplayContrl::Move(ctx.name.clone(), twid, after)));
+                }
+            }
+
+            user_input::UserInput::ClearQueue => {
+                print!("clear queue");
+
+                for id in ctx.clear_queue() {
+                    self.outputs.push(Output::Display(display_bridge::DisplayContrl::Delete(ctx.name.clone(), id)));
+                }
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::Paused(msg) => {
+                self.settings.paused = msg;
+            }
+
+            user_input::UserInput::Speaker(speaker) => {
+                println!("{:?}", speaker);
+                self.settings.addr = speaker.addr;
+                self.settings.speaker = speaker.speaker;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::SpeechRate(speech_rate) => {
+                self.settings.speech_rate = speech_rate;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::EmojiMode(mode) => {
+                self.settings.emoji.mode = mode;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::EmojiOverrides(overrides) => {
+                self.settings.emoji.overrides = overrides;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::AuthorRules(rules) => {
+                self.settings.author_rules = rules;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::AutoVoice(auto_voice) => {
+                self.settings.auto_voice = auto_voice;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::MuteRules(rules) => {
+                self.settings.mute = mute_rules::MuteFilter::new(rules);
+            }
+
+            user_input::UserInput::PriorityRules(rules) => {
+                self.settings.priority = rules;
+            }
+
+            user_input::UserInput::LanguageVoices(voices) => {
+                self.settings.language_voices = voices;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::Translation(translation) => {
+                self.settings.translation = translation;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::CatchUp(mut catch_up) => {
+                // The backlog stops growing at the wait list, a higher threshold is never reached
+                catch_up.threshold = catch_up.threshold.min(WAIT_LIST_MAX - 1);
+                self.settings.catch_up = catch_up;
+
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::Recording(record) => {
+                self.settings.record = record.clone();
+                self.outputs.push(Output::Record(recorder::RecorderControl::Settings(record)));
+            }
+
+            user_input::UserInput::UserDictUpdated => {
+                self.replay.clear();
+                ctx.remove_cache();
+                self.tts_jobs.cancel();
+            }
+
+            user_input::UserInput::TimelineView(timeline) => {
+                println!("scheduler: {:?}", timeline);
+
+                if timeline != self.current_tl_view {
+                    self.outputs.push(Output::Audio(audio_player::AudioControl::Stop));
+                    ctx.remove_cache();
+                    self.tts_jobs.cancel();
+
+                    match timeline.clone() {
+                        twitter_agent::Timeline::User => {
+                            ctx = &mut self.ctx_user;
+                        }
+
+                        twitter_agent::Timeline::Search {..} => {
+                            ctx = &mut self.ctx_search;
+                        }
+                    }
+                }
+
+                self.current_tl_view = timeline;
+
+                if ctx.forcus_id.is_some() {
+                    self.outputs.push(Output::Display(display_bridge::DisplayContrl::Scroll(ctx.name.clone(), ctx.forcus_id.as_ref().unwrap().clone())));
+                }
+            }
+        }
+    }
 
+    // Proceed the timeline in view as far as the state allows
+    fn proceed(&mut self) {
+        let ctx = match self.current_tl_view {
+            twitter_agent::Timeline::User => &mut self.ctx_user,
+            twitter_agent::Timeline::Search {..} => &mut self.ctx_search,
+        };
+
+        // Catch up
+        let count = self.settings.catch_up.skip_count(ctx.queued_len());
+        if count > 0 {
+            println!("scheduler: skip ahead {:?}", count);
+            for id in ctx.skip_ahead(count).into_iter().filter(|id| !is_announcement(id)) {
+                self.outputs.push(Output::Display(display_bridge::DisplayContrl::Delete(ctx.name.clone(), id)));
+            }
+            self.tts_jobs.retain(|id| ctx.is_queued(id));
+        }
+
+        // TTS Start
+        // Jobs canceled meanwhile leave their tweets waiting again
+        ctx.queue.release(|id| self.tts_jobs.is_processing(id));
+
+        while self.tts_jobs.can_dispatch()
+            && ctx.ready_len() + self.tts_jobs.len() < READY_LIST_MAX {
+
+            let r = match ctx.fetch_for_tts() {
+                Some(r) => r,
+                None => break,
+            };
+            println!("scheduler: start processing {:?}", r.tweet_id);
+
+            let mut playbook = self.settings.playbook(r, ctx.queued_len());
+            let cache_key = replay_cache::ReplayCache::key(&playbook);
+
+            // Read before with the same voice
+            if let Some(speech) = self.replay.get(cache_key.as_str(), &playbook) {
+                println!("scheduler: replay cache hit {:?}", playbook.tweet_id);
+                ctx.add_tss_result(Ok(speech));
+                continue;
+            }
+
+            self.tts_jobs.start(&mut playbook, cache_key);
+            self.outputs.push(Output::Synthesize(playbook));
+        }
+
+        // Play speech
+        if self.audio_ready && ctx.is_speech_ready() {
+            println!("Audio and speech is ready, start playing.");
+            self.audio_ready = false;
+            let (record, speech, overflow) = ctx.fetch_for_playback();
+            self.playing_id = Some(speech.tweet_id.clone());
+
+            if record.priority && self.settings.priority.chime {
+                self.outputs.push(Output::Audio(audio_player::AudioControl::Chime));
+            }
+
+            // For the gain of the engine
+            let engine = self.settings.speakers
+                .iter()
+                .find(|s| s.addr == speech.addr)
+                .map(|s| s.engine.clone())
+                .unwrap_or_default();
+            self.outputs.push(Output::Audio(audio_player::AudioControl::Engine(engine)));
+
+            // Nothing is copied for the recorder unless it records
+            if self.settings.record.mode != recorder::RecordMode::Off && !is_announcement(&speech.tweet_id) {
+                let entry = recorder::Entry{record: record.clone(), speech: speech.clone()};
+                self.outputs.push(Output::Record(recorder::RecorderControl::Append(entry)));
+            }
+
+            let voice_pack = vec![speech.name, speech.text]
+                .into_iter()
+                .filter(|v| !v.is_empty())
+                .collect();
+            self.outputs.push(Output::Audio(audio_player::AudioControl::PlayMulti(voice_pack)));
+
+            if let Some(twid) = overflow {
+                self.outputs.push(Output::Display(display_bridge::DisplayContrl::Delete(ctx.name.clone(), twid)));
+            }
+
+            // Announcements are not displayed
+            if !is_announcement(&speech.tweet_id) {
+                ctx.forcus_id = Some(speech.tweet_id);
+                self.outputs.push(Output::Display(display_bridge::DisplayContrl::Scroll(ctx.name.clone(), ctx.forcus_id.as_ref().unwrap().clone())));
+            }
+        }
+    }
+}
 
 pub fn start(
-    _app_handle: tauri::AppHandle,
+    host: host::Host,
     display_tx: tokio::sync::mpsc::Sender<display_bridge::DisplayContrl>,
     playbook_tx: tokio::sync::mpsc::Sender<voicegen_agent::Playbook>,
     audioctl_tx: tokio::sync::mpsc::Sender<audio_player::AudioControl>,
+    recorder_tx: tokio::sync::mpsc::Sender<recorder::RecorderControl>,
     mut user_tl_rx: tokio::sync::mpsc::Receiver<Record>,
     mut search_tl_rx: tokio::sync::mpsc::Receiver<(twitter_agent::Timeline, Record)>,
-    mut speech_rx: tokio::sync::mpsc::Receiver<Option<voicegen_agent::Speech>>,
+    mut speech_rx: tokio::sync::mpsc::Receiver<voicegen_agent::TTSResult>,
     mut audioctl_rdy_rx: tokio::sync::mpsc::Receiver<audio_player::AudioControlRdy>,
     mut user_rx: tokio::sync::mpsc::Receiver<user_input::UserInput>,
+    mut speakers_rx: tokio::sync::mpsc::Receiver<Vec<voicegen_observer::Speaker>>,
 ) {
-    // Context
-    let mut current_tl_view = twitter_agent::Timeline::User;
-    let mut current_search_tl = twitter_agent::Timeline::Search{query: "".to_string()};
-    let mut ctx_user = Context::new("user".to_string());
-    let mut ctx_search = Context::new("search".to_string());
-    let mut tts_state = TTSState::Waiting;
-    let mut settings = Settings::new();
-
-    // Operating clock
-    let (clk_tx, mut clk_rx) = tokio::sync::mpsc::channel::<()>(1);
+    let replay = replay_cache::ReplayCache::load(host.path(replay_cache::DIR_NAME));
+    let mut scheduler = Scheduler::new(replay);
+
     tokio::spawn(async move {
         loop {
-            tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-            let _ = clk_tx.send(()).await;
+            scheduler.print_status();
+
+            let user_tl_open = scheduler.accepts(&twitter_agent::Timeline::User);
+            let search_tl_open = scheduler.accepts(&twitter_agent::Timeline::Search{query: "".to_string()});
+
+            print!("scheduler: Select> ");
+            let input = tokio::select! {
+                Some(msg) = user_tl_rx.recv(), if user_tl_open => Input::Tweet(twitter_agent::Timeline::User, msg),
+                Some((tl, msg)) = search_tl_rx.recv(), if search_tl_open => Input::Tweet(tl, msg),
+                Some(result) = speech_rx.recv() => Input::Speech(result),
+                Some(rdy) = audioctl_rdy_rx.recv() => match rdy.error {
+                    None => Input::AudioReady,
+                    Some(error) => Input::AudioFailed(error),
+                },
+                Some(speakers) = speakers_rx.recv() => Input::Speakers(speakers),
+                Some(user) = user_rx.recv() => Input::User(user),
+
+                else => {
+                    println!("Core thread exit");
+                    return ();
+                }
+            };
+
+            for output in scheduler.handle(input) {
+                match output {
+                    Output::Display(msg) => display_tx.send(msg).await.unwrap(),
+                    Output::Synthesize(playbook) => playbook_tx.send(playbook).await.unwrap(),
+                    Output::Audio(msg) => audioctl_tx.send(msg).await.unwrap(),
+                    Output::Record(msg) => recorder_tx.send(msg).await.unwrap(),
+                }
+            }
         }
     });
+}
 
-    tokio::spawn(async move {
-        let mut ctx = &mut ctx_user;
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn speaker(engine: &str, id: u64) -> voicegen_observer::Speaker {
+        voicegen_observer::Speaker {
+            addr: std::net::SocketAddr::from(([127, 0, 0, 1], 50021)),
+            engine: engine.to_string(),
+            name: format!("speaker{}", id),
+            style: "normal".to_string(),
+            speaker: id,
+        }
+    }
 
-        loop {
-            println!("");
-            println!(
-                "current_search_tl: {:?}",
-                current_search_tl
-            );
-            println!(
-                "current_tl_view: {:?}",
-                current_tl_view
-            );
-            println!(
-                "tts_state: {:?}",
-                tts_state,
-            );
-            println!(
-                "setting: {:?}, {:?}, {:?}, {:?}",
-                settings.addr,
-                settings.speaker,
-                settings.speech_rate,
-                settings.paused,
-            );
-            println!(
-                "ctx: {:?}, {:?}, {:?}, {:?}, {:?}, {:?}, {:?}",
-                ctx.name,
-                ctx.forcus_id,
-                ctx.wait_list.len(),
-                ctx.ready_list.len(),
-                ctx.played_list.len(),
-                ctx.speech_cache.len(),
-                tts_state,
-            );
+    fn record(twid: &str) -> Record {
+        Record {
+            tweet_id: twid.to_string(),
+            author_id: "100".to_string(),
+            created_at: "".to_string(),
+            text: "text".to_string(),
+            name: "name".to_string(),
+            username: "user".to_string(),
+            profile_image_url: "".to_string(),
+            attachments: vec![],
+            lang: "ja".to_string(),
+            is_retweet: false,
+            is_reply: false,
+            priority: false,
+        }
+    }
 
-            print!("scheduler: Select> ");
-            tokio::select! {
-                Some(_) = clk_rx.recv() => {
-                    // Obtain Tweet
-
-                    ctx = &mut ctx_user;
-                    if !settings.paused && ctx.wait_list.len() < WAIT_LIST_MAX{
-                        match user_tl_rx.try_recv() {
-                            Ok(msg) => {
-                                println!("user tl New tweet incoming {:?}", msg.tweet_id);
-
-                                display_tx.send(display_bridge::DisplayContrl::Add(ctx.name.clone(), msg.clone().into())).await.unwrap();
-                                ctx.add_new_tweet(&msg);
-                            }
-
-                            Err(e) => {
-                                match e {
-                                    tokio::sync::mpsc::error::TryRecvError::Empty => {},
-
-                                    e => {
-                                        println!("scheduler: twitter agent closes pci {:?}", e);
-                                        return ();
-                                    }
-                                }
-                            },
-                        }
-                    }
+    fn queued(ctx: &Context) -> Vec<&str> {
+        ctx.queue.queued().map(|x| x.record.tweet_id.as_str()).collect()
+    }
 
-                    ctx = &mut ctx_search;
-                    if !settings.paused && ctx.wait_list.len() < WAIT_LIST_MAX {
-                        match search_tl_rx.try_recv() {
-                            Ok((tl, msg)) => {
-                                println!("search tl New tweet incoming {:?}", msg.tweet_id);
-
-                                match tl {
-                                    twitter_agent::Timeline::User => { /* not used */ }
-
-                                    twitter_agent::Timeline::Search {ref query} => {
-                                        if tl != current_search_tl {
-                                            println!("scheduler: drop all");
-                                            audioctl_tx.send(audio_player::AudioControl::Stop).await.unwrap();
-                                            ctx.forcus_id = None;
-
-                                            for id in ctx.drop_all() {
-                                                display_tx.send(display_bridge::DisplayContrl::Delete(ctx.name.clone(), id)).await.unwrap();
-                                            }
-                                            if tts_state == TTSState::Processing {
-                                                tts_state = TTSState::Canceling;
-                                                println!("scheduler: cancelling");
-                                            }
-
-                                            current_search_tl = tl.clone();
-                                        }
-                                    }
-                                }
-
-                                display_tx.send(display_bridge::DisplayContrl::Add(ctx.name.clone(), msg.clone().into())).await.unwrap();
-                                ctx.add_new_tweet(&msg);
-
-                            }
-
-                            Err(e) => {
-                                match e {
-                                    tokio::sync::mpsc::error::TryRecvError::Empty => {},
-
-                                    e => {
-                                        println!("scheduler: twitter agent closes pci {:?}", e);
-                                        return ();
-                                    }
-                                }
-                            },
-                        }
-                    }
+    fn played(ctx: &Context) -> Vec<&str> {
+        ctx.queue.played().map(|x| x.record.tweet_id.as_str()).collect()
+    }
 
-                    ctx = match current_tl_view.clone() {
-                        twitter_agent::Timeline::User => { &mut ctx_user }
-                        twitter_agent::Timeline::Search {query} => { &mut ctx_search }
-                    };
+    fn scheduler() -> Scheduler {
+        Scheduler::new(replay_cache::ReplayCache::load(None))
+    }
 
-                    // TTS Start
-                    if ctx.wait_list.len() > 0 
-                        && ctx.ready_list.len() < READY_LIST_MAX 
-                        && tts_state == TTSState::Waiting {
+    fn tweet(twid: &str) -> Input {
+        Input::Tweet(twitter_agent::Timeline::User, record(twid))
+    }
 
-                        tts_state = TTSState::Processing;
-                        let r = ctx.fetch_for_tts();
-                        println!("<clk>start processing {:?}", r.tweet_id);
+    fn search_tweet(query: &str, twid: &str) -> Input {
+        Input::Tweet(twitter_agent::Timeline::Search{query: query.to_string()}, record(twid))
+    }
 
-                        playbook_tx.send(
-                            voicegen_agent::into(r, settings.addr, settings.speaker, settings.speech_rate)
-                            ).await.unwrap();
+    fn search_view(query: &str) -> Input {
+        Input::User(user_input::UserInput::TimelineView(twitter_agent::Timeline::Search{query: query.to_string()}))
+    }
 
-                    }
+    fn synthesized(outputs: &[Output]) -> Vec<voicegen_agent::Playbook> {
+        outputs
+            .iter()
+            .filter_map(|o| match o {
+                Output::Synthesize(playbook) => Some(playbook.clone()),
+                _ => None,
+            })
+            .collect()
+    }
 
-                    // Process TTS Result
-                    match speech_rx.try_recv() {
-                        Ok(speech) => {
-                            if tts_state != TTSState::Canceling {
-                                ctx.add_tss_result(speech);
-                            } else {
-                                println!("tts result is ignored");
-                            }
-
-                            tts_state = TTSState::Waiting;
-                        },
-
-                        Err(e) => {
-                            match e {
-                                tokio::sync::mpsc::error::TryRecvError::Empty => {},
-
-                                e => {
-                                    println!("scheduler: voicegen_agent closes pci {:?}", e);
-                                    return ();
-                                }
-                            }
-                        },
-                    }
+    fn tweet_ids(playbooks: &[voicegen_agent::Playbook]) -> Vec<&str> {
+        playbooks.iter().map(|p| p.tweet_id.as_str()).collect()
+    }
 
-                    // Play speech
-                    if ctx.is_speech_ready() {
-                        match audioctl_rdy_rx.try_recv() {
-                            Ok(_) => {
-                                println!("Audio and speech is ready, start playing.");
-                                let (speech, overflow) = ctx.fetch_for_playback();
-
-                                let voice_pack = vec![speech.name, speech.text];
-                                audioctl_tx.send(audio_player::AudioControl::PlayMulti(voice_pack)).await.unwrap();
-
-                                if let Some(twid) = overflow {
-                                    display_tx.send(display_bridge::DisplayContrl::Delete(ctx.name.clone(), twid)).await.unwrap();
-                                }
-
-                                ctx.forcus_id = Some(speech.tweet_id);
-                                display_tx.send(display_bridge::DisplayContrl::Scroll(ctx.name.clone(), ctx.forcus_id.as_ref().unwrap().clone())).await.unwrap();
-                            },
-
-                            Err(e) => {
-                                match e {
-                                    tokio::sync::mpsc::error::TryRecvError::Empty => {},
-
-                                    e => {
-                                        println!("scheduler: audio player closes pci {:?}", e);
-                                        return ();
-                                    }
-                                }
-                            },
-                        }
-                    }
+    fn synthesis_done(playbook: &voicegen_agent::Playbook) -> Input {
+        Input::Speech(Ok(voicegen_agent::Speech {
+            tweet_id: playbook.tweet_id.clone(),
+            job_id: playbook.job_id,
+            addr: playbook.addr,
+            text: vec![1],
+            name: vec![],
+            cacheable: true,
+        }))
+    }
 
-                }
+    fn deleted(outputs: &[Output]) -> Vec<&str> {
+        outputs
+            .iter()
+            .filter_map(|o| match o {
+                Output::Display(display_bridge::DisplayContrl::Delete(_, twid)) => Some(twid.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
 
-                Some(user) = user_rx.recv() => {
-                    print!("User input - ");
-                    match user {
-                        user_input::UserInput::Jump(twid) => {
-                            print!("jump to {:?}", twid);
-                            audioctl_tx.send(audio_player::AudioControl::Stop).await.unwrap();
+    fn scrolled(outputs: &[Output]) -> Vec<&str> {
+        outputs
+            .iter()
+            .filter_map(|o| match o {
+                Output::Display(display_bridge::DisplayContrl::Scroll(_, twid)) => Some(twid.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
 
-                            // Cancel current playing speech only;
-                            if twid == "" { continue; }
+    fn plays(outputs: &[Output]) -> usize {
+        outputs
+            .iter()
+            .filter(|o| matches!(o, Output::Audio(audio_player::AudioControl::PlayMulti(_))))
+            .count()
+    }
 
-                            if tts_state == TTSState::Processing {
-                                tts_state = TTSState::Canceling;
-                            }
-                            let drop_list = ctx.jump_to_twid(&twid);
+    fn engines(outputs: &[Output]) -> Vec<&str> {
+        outputs
+            .iter()
+            .filter_map(|o| match o {
+                Output::Audio(audio_player::AudioControl::Engine(engine)) => Some(engine.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
 
-                            for id in drop_list {
-                                display_tx.send(display_bridge::DisplayContrl::Delete(ctx.name.clone(), id)).await.unwrap();
-                            }
+    fn stops(outputs: &[Output]) -> bool {
+        outputs
+            .iter()
+            .any(|o| matches!(o, Output::Audio(audio_player::AudioControl::Stop)))
+    }
 
-                            ctx.forcus_id = Some(twid);
-                            display_tx.send(display_bridge::DisplayContrl::Scroll(ctx.name.clone(), ctx.forcus_id.as_ref().unwrap().clone())).await.unwrap();
-                        },
+    #[test]
+    fn ts_scheduler_plays_in_order() {
+        let mut s = scheduler();
 
-                        user_input::UserInput::Paused(msg) => {
-                            settings.paused = msg;
-                        }
+        let first = synthesized(&s.handle(tweet("1")));
+        let second = synthesized(&s.handle(tweet("2")));
+        assert_eq!(tweet_ids(&first), vec!["1"]);
+        assert_eq!(tweet_ids(&second), vec!["2"]);
 
-                        user_input::UserInput::Speaker(speaker) => {
-                            println!("{:?}", speaker);
-                            settings.addr = speaker.addr;
-                            settings.speaker = speaker.speaker;
+        // Nothing is played until the player is ready
+        assert_eq!(plays(&s.handle(synthesis_done(&second[0]))), 0);
+        assert_eq!(plays(&s.handle(Input::AudioReady)), 0);
 
-                            ctx.remove_cache();
-                            if tts_state == TTSState::Processing {
-                                tts_state = TTSState::Canceling;
-                            }
-                        }
+        let out = s.handle(synthesis_done(&first[0]));
+        assert_eq!(plays(&out), 1);
+        assert_eq!(scrolled(&out), vec!["1"]);
 
-                        user_input::UserInput::SpeechRate(speech_rate) => {
-                            settings.speech_rate = speech_rate;
+        let out = s.handle(Input::AudioReady);
+        assert_eq!(plays(&out), 1);
+        assert_eq!(scrolled(&out), vec!["2"]);
 
-                            ctx.remove_cache();
-                            if tts_state == TTSState::Processing {
-                                tts_state = TTSState::Canceling;
-                            }
-                        }
+        assert_eq!(plays(&s.handle(Input::AudioReady)), 0);
+        assert_eq!(played(&s.ctx_user), vec!["1", "2"]);
 
-                        user_input::UserInput::TimelineView(timeline) => {
-                            println!("scheduler: {:?}", timeline);
+        // Results of unknown jobs are ignored
+        assert_eq!(plays(&s.handle(synthesis_done(&first[0]))), 0);
+        assert_eq!(s.ctx_user.queued_len(), 0);
+    }
 
-                            if timeline != current_tl_view {
-                                audioctl_tx.send(audio_player::AudioControl::Stop).await.unwrap();
-                                ctx.remove_cache();
-                                if tts_state == TTSState::Processing {
-                                    tts_state = TTSState::Canceling;
-                                }
+    #[test]
+    fn ts_scheduler_audio_failed() {
+        let mut s = scheduler();
+        s.handle(Input::AudioReady);
+
+        let first = synthesized(&s.handle(tweet("1")));
+        let second = synthesized(&s.handle(tweet("2")));
+        assert_eq!(plays(&s.handle(synthesis_done(&first[0]))), 1);
+        assert_eq!(plays(&s.handle(synthesis_done(&second[0]))), 0);
+
+        // The broken speech is skipped, reading goes on
+        let out = s.handle(Input::AudioFailed(audio_player::AudioError::Decode("".to_string())));
+        assert_eq!(plays(&out), 1);
+        assert_eq!(scrolled(&out), vec!["2"]);
+        assert_eq!(s.playing_id, Some("2".to_string()));
+
+        let out = s.handle(Input::AudioFailed(audio_player::AudioError::Output("".to_string())));
+        assert_eq!(plays(&out), 0);
+        assert!(s.audio_ready);
+    }
 
-                                match timeline.clone() {
-                                    twitter_agent::Timeline::User => {
-                                        ctx = &mut ctx_user;
-                                    }
+    #[test]
+    fn ts_scheduler_jump() {
+        let mut s = scheduler();
+        let mut jobs = vec![];
+        for id in ["1", "2", "3", "4"] {
+            jobs.extend(synthesized(&s.handle(tweet(id))));
+        }
+        assert_eq!(tweet_ids(&jobs), vec!["1", "2", "3", "4"]);
+
+        let out = s.handle(Input::User(user_input::UserInput::Jump("3".to_string())));
+        assert!(stops(&out));
+        assert_eq!(scrolled(&out), vec!["3"]);
+        assert_eq!(played(&s.ctx_user), vec!["1", "2"]);
+        assert_eq!(queued(&s.ctx_user), vec!["3", "4"]);
+
+        // Jobs of skipped tweets are canceled
+        let cancelled: Vec<bool> = jobs.iter().map(|p| p.cancel.is_cancelled()).collect();
+        assert_eq!(cancelled, vec![true, true, false, false]);
+        s.handle(synthesis_done(&jobs[0]));
+        assert_eq!(queued(&s.ctx_user), vec!["3", "4"]);
+
+        // Jumping into the history reads it again
+        let out = s.handle(Input::User(user_input::UserInput::Jump("1".to_string())));
+        assert!(stops(&out));
+        assert_eq!(scrolled(&out), vec!["1"]);
+        assert_eq!(tweet_ids(&synthesized(&out)), vec!["1", "2"]);
+        assert_eq!(queued(&s.ctx_user), vec!["1", "2", "3", "4"]);
+
+        // An empty id only stops the current speech
+        let out = s.handle(Input::User(user_input::UserInput::Jump("".to_string())));
+        assert!(stops(&out));
+        assert!(scrolled(&out).is_empty());
+        assert_eq!(queued(&s.ctx_user), vec!["1", "2", "3", "4"]);
+    }
 
-                                    twitter_agent::Timeline::Search {query} => {
-                                        ctx = &mut ctx_search;
-                                    }
-                                }
-                            }
+    #[test]
+    fn ts_scheduler_timeline_switch() {
+        let mut s = scheduler();
+        let mut user_jobs = vec![];
+        for id in ["1", "2"] {
+            user_jobs.extend(synthesized(&s.handle(tweet(id))));
+        }
 
-                            current_tl_view = timeline;
+        // Only the timeline in view is synthesized, the first query restarts its jobs
+        let out = s.handle(search_tweet("a", "10"));
+        assert!(stops(&out));
+        assert!(user_jobs.iter().all(|p| p.cancel.is_cancelled()));
+        let user_jobs = synthesized(&out);
+        assert_eq!(tweet_ids(&user_jobs), vec!["1", "2"]);
+        assert!(synthesized(&s.handle(search_tweet("a", "11"))).is_empty());
+
+        let out = s.handle(search_view("a"));
+        assert!(stops(&out));
+        assert!(user_jobs.iter().all(|p| p.cancel.is_cancelled()));
+        let search_jobs = synthesized(&out);
+        assert_eq!(tweet_ids(&search_jobs), vec!["10", "11"]);
+
+        // A new query drops the previous results
+        let out = s.handle(search_tweet("b", "20"));
+        assert!(stops(&out));
+        assert_eq!(deleted(&out), vec!["10", "11"]);
+        assert!(search_jobs.iter().all(|p| p.cancel.is_cancelled()));
+        assert_eq!(tweet_ids(&synthesized(&out)), vec!["20"]);
+        assert_eq!(queued(&s.ctx_search), vec!["20"]);
+
+        let out = s.handle(Input::User(user_input::UserInput::TimelineView(twitter_agent::Timeline::User)));
+        assert!(stops(&out));
+        assert_eq!(tweet_ids(&synthesized(&out)), vec!["1", "2"]);
+        assert_eq!(queued(&s.ctx_user), vec!["1", "2"]);
+    }
 
-                            if ctx.forcus_id.is_some() {
-                                display_tx.send(display_bridge::DisplayContrl::Scroll(ctx.name.clone(), ctx.forcus_id.as_ref().unwrap().clone())).await.unwrap();
-                            }
-                        }
-                    }
-                }
+    #[test]
+    fn ts_scheduler_speaker_change() {
+        let mut s = scheduler();
+        let jobs = synthesized(&s.handle(tweet("1")));
+        s.handle(synthesis_done(&jobs[0]));
+        assert_eq!(s.ctx_user.ready_len(), 1);
+
+        // The ready speech is synthesized again with the new voice
+        let out = s.handle(Input::User(user_input::UserInput::Speaker(speaker("VOICEVOX", 3))));
+        let jobs = synthesized(&out);
+        assert_eq!(tweet_ids(&jobs), vec!["1"]);
+        assert_eq!(jobs[0].speaker, 3);
+        assert_eq!(s.ctx_user.ready_len(), 0);
+        assert_eq!(plays(&s.handle(Input::AudioReady)), 0);
+
+        // Speaker lists matter to automatic voices only
+        let out = s.handle(Input::Speakers(vec![speaker("VOICEVOX", 5)]));
+        assert!(synthesized(&out).is_empty());
+
+        let auto_voice = AutoVoice {
+            enabled: true,
+            pool: vec![],
+        };
+        let out = s.handle(Input::User(user_input::UserInput::AutoVoice(auto_voice)));
+        assert!(jobs[0].cancel.is_cancelled());
+        let jobs = synthesized(&out);
+        assert_eq!(jobs[0].speaker, 5);
+
+        let out = s.handle(Input::Speakers(vec![speaker("VOICEVOX", 7)]));
+        assert!(jobs[0].cancel.is_cancelled());
+        let jobs = synthesized(&out);
+        assert_eq!(jobs[0].speaker, 7);
+
+        // The player is told whose voice it is
+        let out = s.handle(synthesis_done(&jobs[0]));
+        assert_eq!(plays(&out), 1);
+        assert_eq!(engines(&out), vec!["VOICEVOX"]);
+    }
 
-                else => {
-                    println!("Core thread exit");
-                    return ();
-                }
-            }
+    #[test]
+    fn ts_scheduler_overflow() {
+        let mut s = scheduler();
+        let mut dropped = vec![];
+        for i in 0..=HISTORY_LENGTH {
+            s.handle(Input::AudioReady);
+            let jobs = synthesized(&s.handle(tweet(i.to_string().as_str())));
+            let out = s.handle(synthesis_done(&jobs[0]));
+            assert_eq!(plays(&out), 1);
+            dropped.extend(deleted(&out).into_iter().map(|id| id.to_string()));
         }
-    });
+
+        // The oldest tweet leaves the history
+        assert_eq!(dropped, vec!["0".to_string()]);
+        assert_eq!(played(&s.ctx_user).len(), HISTORY_LENGTH);
+
+        // Tweets stay in the channel while the wait list is full
+        for i in 0..WAIT_LIST_MAX {
+            assert!(s.accepts(&twitter_agent::Timeline::User));
+            s.handle(tweet(format!("w{}", i).as_str()));
+        }
+        assert!(!s.accepts(&twitter_agent::Timeline::User));
+        assert!(s.accepts(&twitter_agent::Timeline::Search{query: "".to_string()}));
+
+        s.handle(Input::User(user_input::UserInput::Paused(true)));
+        assert!(!s.accepts(&twitter_agent::Timeline::Search{query: "".to_string()}));
+    }
+
+    #[test]
+    fn ts_catch_up_threshold_reached() {
+        let mut s = scheduler();
+        let mut catch_up = catch_up::CatchUpSettings::new();
+        catch_up.mode = catch_up::CatchUpMode::SkipAhead;
+
+        catch_up.threshold = WAIT_LIST_MAX - 1;
+        s.handle(Input::User(user_input::UserInput::CatchUp(catch_up.clone())));
+        assert_eq!(s.settings.catch_up.threshold, WAIT_LIST_MAX - 1);
+
+        catch_up.threshold = WAIT_LIST_MAX;
+        s.handle(Input::User(user_input::UserInput::CatchUp(catch_up)));
+        assert_eq!(s.settings.catch_up.threshold, WAIT_LIST_MAX - 1);
+
+        for i in 0..WAIT_LIST_MAX {
+            assert!(s.accepts(&twitter_agent::Timeline::User));
+            s.handle(tweet(format!("{}", i).as_str()));
+        }
+        assert!(queued(&s.ctx_user).iter().any(|id| is_announcement(id)));
+    }
+
+    #[test]
+    fn ts_rewind_and_replay() {
+        let mut ctx = Context::new("user".to_string());
+        for id in ["1", "2", "3", "4"] {
+            ctx.add_new_tweet(&record(id));
+        }
+        ctx.skip(3);
+
+        assert_eq!(ctx.previous_twid(|_| true), Some("2".to_string()));
+
+        // Muted tweets stay in the history
+        assert!(ctx.rewind_to_twid("2", |r| r.tweet_id != "3"));
+        assert_eq!(played(&ctx), vec!["1", "3"]);
+        assert_eq!(queued(&ctx), vec!["2", "4"]);
+
+        assert!(ctx.replay_twid("1"));
+        assert_eq!(played(&ctx), vec!["3"]);
+        assert_eq!(queued(&ctx), vec!["1", "2", "4"]);
+
+        assert!(!ctx.replay_twid("4"));
+    }
+
+    #[test]
+    fn ts_scheduler_replay_unknown() {
+        let mut s = scheduler();
+        let replay = |twid: &str| Input::User(user_input::UserInput::Replay(twid.to_string()));
+
+        // Nothing to repeat yet
+        assert!(!stops(&s.handle(Input::User(user_input::UserInput::RepeatLast))));
+
+        let first = synthesized(&s.handle(tweet("1")));
+        s.handle(Input::AudioReady);
+        assert_eq!(plays(&s.handle(synthesis_done(&first[0]))), 1);
+
+        // The tweet being read is not cut off for nothing
+        assert!(!stops(&s.handle(replay("unknown"))));
+        let out = s.handle(Input::User(user_input::UserInput::RepeatLast));
+        assert!(stops(&out));
+
+        let mut again = synthesized(&out);
+        again.extend(synthesized(&s.handle(Input::AudioReady)));
+        assert_eq!(plays(&s.handle(synthesis_done(&again[0]))), 1);
+        assert!(stops(&s.handle(replay("1"))));
+    }
+
+    #[test]
+    fn ts_previous_passes_muted() {
+        let mut s = scheduler();
+        let rules = mute_rules::MuteRules {
+            words: vec!["spoiler".to_string()],
+            show_muted: true,
+            ..Default::default()
+        };
+        s.handle(Input::User(user_input::UserInput::MuteRules(rules)));
+
+        let first = synthesized(&s.handle(tweet("1")));
+        s.handle(Input::AudioReady);
+        assert_eq!(plays(&s.handle(synthesis_done(&first[0]))), 1);
+
+        let mut muted = record("2");
+        muted.text = "spoiler".to_string();
+        s.handle(Input::Tweet(twitter_agent::Timeline::User, muted));
+
+        let third = synthesized(&s.handle(tweet("3")));
+        s.handle(Input::AudioReady);
+        assert_eq!(plays(&s.handle(synthesis_done(&third[0]))), 1);
+        assert_eq!(played(&s.ctx_user), vec!["1", "2", "3"]);
+
+        let out = s.handle(Input::User(user_input::UserInput::Previous));
+        assert_eq!(scrolled(&out), vec!["1"]);
+        assert_eq!(queued(&s.ctx_user), vec!["1", "3"]);
+    }
+
+    #[test]
+    fn ts_record_only_when_enabled() {
+        let mut s = scheduler();
+        let records = |out: &[Output]| out.iter().filter(|o| matches!(o, Output::Record(recorder::RecorderControl::Append(_)))).count();
+
+        let first = synthesized(&s.handle(tweet("1")));
+        s.handle(Input::AudioReady);
+        assert_eq!(records(&s.handle(synthesis_done(&first[0]))), 0);
+
+        let mut settings = recorder::RecordSettings::new();
+        settings.mode = recorder::RecordMode::Rolling;
+        s.handle(Input::User(user_input::UserInput::Recording(settings)));
+
+        let second = synthesized(&s.handle(tweet("2")));
+        s.handle(Input::AudioReady);
+        assert_eq!(records(&s.handle(synthesis_done(&second[0]))), 1);
+    }
+
+    #[test]
+    fn ts_queue_commands() {
+        let mut ctx = Context::new("user".to_string());
+        for id in ["1", "2", "3", "4", "5"] {
+            ctx.add_new_tweet(&record(id));
+        }
+
+        assert!(ctx.move_to_front("4"));
+        assert_eq!(queued(&ctx), vec!["4", "1", "2", "3", "5"]);
+
+        assert!(ctx.remove_twid("1"));
+        assert!(!ctx.remove_twid("1"));
+
+        assert!(ctx.skip(2).is_empty());
+        assert_eq!(played(&ctx), vec!["4", "2"]);
+        assert_eq!(queued(&ctx), vec!["3", "5"]);
+
+        assert_eq!(ctx.clear_queue(), vec!["3".to_string(), "5".to_string()]);
+        assert_eq!(ctx.queued_len(), 0);
+        assert_eq!(played(&ctx).len(), 2);
+    }
+
+    #[test]
+    fn ts_priority_order() {
+        let mut ctx = Context::new("user".to_string());
+        for (id, priority) in [("1", false), ("2", true), ("3", false), ("4", true)] {
+            let mut r = record(id);
+            r.priority = priority;
+            ctx.add_new_tweet(&r);
+        }
+
+        // Ahead of the others, in the order they came
+        assert_eq!(queued(&ctx), vec!["2", "4", "1", "3"]);
+
+        // Behind the tweet being read
+        ctx.skip(1);
+        let mut r = record("5");
+        r.priority = true;
+        ctx.add_new_tweet(&r);
+        assert_eq!(queued(&ctx), vec!["4", "5", "1", "3"]);
+    }
+
+    #[test]
+    fn ts_move_to_front_displayed() {
+        let mut s = scheduler();
+        let first = synthesized(&s.handle(tweet("1")));
+        s.handle(Input::AudioReady);
+        s.handle(synthesis_done(&first[0]));
+        s.handle(tweet("2"));
+        s.handle(tweet("3"));
+
+        let out = s.handle(Input::User(user_input::UserInput::MoveToFront("3".to_string())));
+        assert_eq!(queued(&s.ctx_user), vec!["3", "2"]);
+        assert!(out.iter().any(|o| matches!(o,
+            Output::Display(display_bridge::DisplayContrl::Move(_, twid, Some(after))) if twid == "3" && after == "1")));
+
+        // Nothing is moved for a tweet not queued
+        let out = s.handle(Input::User(user_input::UserInput::MoveToFront("1".to_string())));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn ts_skip_ahead() {
+        let mut ctx = Context::new("user".to_string());
+        for id in ["1", "2", "3", "4"] {
+            ctx.add_new_tweet(&record(id));
+        }
+
+        ctx.skip_ahead(3);
+        assert_eq!(played(&ctx), vec!["1", "2", "3"]);
+        assert_eq!(queued(&ctx), vec!["announcement-1-3", "4"]);
+        assert_eq!(ctx.queue.first_queued().unwrap().record.name, "");
+    }
+
+    #[test]
+    fn ts_skip_ahead_priority() {
+        let mut ctx = Context::new("user".to_string());
+        for id in ["1", "2", "3", "4", "5"] {
+            let mut r = record(id);
+            r.priority = id == "2";
+            ctx.add_new_tweet(&r);
+        }
+        ctx.queue.set_state("2", tweet_queue::State::Synthesizing);
+
+        ctx.skip_ahead(3);
+        assert_eq!(played(&ctx), vec!["1", "3", "4"]);
+        assert_eq!(queued(&ctx), vec!["2", "announcement-1-4", "5"]);
+        assert_eq!(ctx.queue.state("2"), Some(tweet_queue::State::Synthesizing));
+    }
+
+    #[test]
+    fn ts_author_voice_first() {
+        let mut settings = Settings::new();
+        settings.language_voices.insert("ja".to_string(), speaker("VOICEVOX", 2));
+        settings.auto_voice = AutoVoice {
+            enabled: true,
+            pool: vec![speaker("VOICEVOX", 3)],
+        };
+        settings.speakers = vec![speaker("VOICEVOX", 3)];
+
+        let mut rule = author_rules::AuthorRule {
+            author_id: "100".to_string(),
+            speaker: Some(speaker("VOICEVOX", 1)),
+            speech_rate: None,
+            nickname: None,
+            read_name: true,
+        };
+        settings.author_rules.insert("100".to_string(), rule.clone());
+        assert_eq!(settings.voice("ja", "100").1, 1);
+
+        // Then the voice of the language, then an automatic one
+        rule.speaker = None;
+        settings.author_rules.insert("100".to_string(), rule);
+        assert_eq!(settings.voice("ja", "100").1, 2);
+        assert_eq!(settings.voice("en", "100").1, 3);
+    }
+
+    #[test]
+    fn ts_translation_voice() {
+        let mut settings = Settings::new();
+        settings.translation.enabled = true;
+        settings.language_voices.insert("en".to_string(), speaker("VOICEVOX", 1));
+        settings.language_voices.insert("ja".to_string(), speaker("VOICEVOX", 2));
+
+        let mut english = record("1");
+        english.lang = "en".to_string();
+        let playbook = settings.playbook(english, 0);
+        assert_eq!((playbook.lang.as_str(), playbook.speaker), ("ja", 2));
+
+        // Read in the voice of the original language should the translation fail
+        let job = playbook.translation.unwrap();
+        assert_eq!((job.source.as_str(), job.source_speaker), ("en", 1));
+    }
+
+    #[test]
+    fn ts_assign_voice_stable() {
+        let available = vec![speaker("VOICEVOX", 0), speaker("VOICEVOX", 1), speaker("VOICEVOX", 2)];
+        let mut reversed = available.clone();
+        reversed.reverse();
+
+        let a = assign_voice("12345", &available, &[]).unwrap();
+        let b = assign_voice("12345", &reversed, &[]).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn ts_assign_voice_pool() {
+        let available = vec![speaker("VOICEVOX", 0), speaker("VOICEVOX", 1), speaker("VOICEVOX", 2)];
+        let pool = vec![speaker("VOICEVOX", 1), speaker("COEIROINK", 9)];
+
+        for id in ["1", "22", "333", "4444"] {
+            assert_eq!(assign_voice(id, &available, &pool).unwrap().speaker, 1);
+        }
+        assert!(assign_voice("1", &available, &[speaker("COEIROINK", 9)]).is_none());
+    }
 }
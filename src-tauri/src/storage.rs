@@ -0,0 +1,75 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+#[cfg(feature = "app")]
+pub fn path(app_handle: &tauri::AppHandle, name: &str) -> Option<std::path::PathBuf> {
+    app_handle
+        .path_resolver()
+        .app_data_dir()
+        .map(|dir| dir.join(name))
+}
+
+pub fn load<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let json = std::fs::read_to_string(path).ok()?;
+
+    match serde_json::from_str::<T>(json.as_str()) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            println!("storage: failed to parse {:?} {:?}", path, e);
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    let json = to_json(path, value)?;
+
+    std::fs::write(path, json)
+}
+
+// For credentials, the file is readable by the user only
+pub fn save_private<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    let json = to_json(path, value)?;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path)?;
+    // The mode is only given to new files
+    #[cfg(unix)]
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+
+    file.write_all(json.as_bytes())
+}
+
+fn to_json<T: Serialize>(path: &Path, value: &T) -> std::io::Result<String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    serde_json::to_string_pretty(value)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn ts_save_private() {
+        let path = std::env::temp_dir().join("twradio_storage_test").join("private.json");
+        save(path.as_path(), &"public").unwrap();
+        save_private(path.as_path(), &"private").unwrap();
+
+        let mode = std::fs::metadata(path.as_path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(load::<String>(path.as_path()), Some("private".to_string()));
+    }
+}
@@ -0,0 +1,208 @@
+// Playback speed without changing the pitch, by WSOLA (waveform similarity overlap-add):
+// windows of the input are overlapped at a fixed hop in the output,
+// taken at a hop scaled by the speed in the input, each shifted a little
+// to line up with the waveform of the previous one.
+
+pub const SPEED_MIN: f64 = 0.5;
+pub const SPEED_MAX: f64 = 3.0;
+
+const WINDOW_MS: u32 = 30;
+// How far a window may be shifted
+const SEEK_MS: u32 = 8;
+
+pub struct TimeStretch {
+    // Interleaved by channel
+    samples: Vec<f32>,
+    // Channels mixed, for lining windows up
+    mono: Vec<f32>,
+    channels: usize,
+    window: Vec<f32>,
+    hop: usize,
+    seek: usize,
+    // Where the next window is looked for, in input frames
+    pos: f64,
+    // Start of the last window taken
+    last: Option<usize>,
+    // Second half of the last window, faded out, to overlap the next one
+    tail: Vec<f32>,
+    done: bool,
+}
+
+impl TimeStretch {
+    pub fn new(samples: Vec<f32>, channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        let hop = ((sample_rate * WINDOW_MS / 1000) as usize / 2).max(1);
+
+        // Periodic Hann, which adds up to one at half overlap
+        let window = (0..hop * 2)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (hop * 2) as f32).cos())
+            .collect();
+
+        let mono = samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+
+        TimeStretch {
+            samples,
+            mono,
+            channels,
+            window,
+            hop,
+            seek: (sample_rate * SEEK_MS / 1000) as usize,
+            pos: 0.0,
+            last: None,
+            tail: vec![0.0; hop * channels],
+            done: false,
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.mono.len()
+    }
+
+    // Zero past the end
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        self.samples
+            .get(frame * self.channels + channel)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    // Start near the target most alike what follows the last window
+    fn align(&self, natural: usize, target: usize) -> usize {
+        let mono = |i: usize| self.mono.get(i).copied().unwrap_or(0.0) as f64;
+        let similarity = |start: usize| {
+            let (mut dot, mut energy) = (0.0f64, 0.0f64);
+            for i in 0..self.hop {
+                dot += mono(natural + i) * mono(start + i);
+                energy += mono(start + i) * mono(start + i);
+            }
+            if energy > 0.0 {
+                dot / energy.sqrt()
+            } else {
+                0.0
+            }
+        };
+
+        // Ties go to the target
+        let mut best = (target, similarity(target));
+        let end = (target + self.seek).min(self.frames());
+        for start in target.saturating_sub(self.seek)..end {
+            let s = similarity(start);
+            if s > best.1 {
+                best = (start, s);
+            }
+        }
+
+        best.0
+    }
+
+    // Next hop of output, interleaved. None once the input is used up.
+    pub fn next_hop(&mut self, speed: f64) -> Option<Vec<f32>> {
+        if self.done {
+            return None;
+        }
+
+        let target = self.pos.round() as usize;
+        if target >= self.frames() {
+            self.done = true;
+            return None;
+        }
+
+        let start = match self.last {
+            Some(last) => self.align(last + self.hop, target),
+            None => target,
+        };
+
+        let mut out = Vec::with_capacity(self.hop * self.channels);
+        for i in 0..self.hop {
+            for c in 0..self.channels {
+                let x = self.sample(start + i, c);
+                // Nothing to overlap with at first
+                out.push(match self.last {
+                    Some(_) => self.tail[i * self.channels + c] + x * self.window[i],
+                    None => x,
+                });
+                self.tail[i * self.channels + c] =
+                    self.sample(start + self.hop + i, c) * self.window[self.hop + i];
+            }
+        }
+
+        self.last = Some(start);
+        self.pos += self.hop as f64 * speed.clamp(SPEED_MIN, SPEED_MAX);
+
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tone(freq: f32, ms: u32) -> Vec<f32> {
+        (0..24 * ms)
+            .map(|i| 0.5 * (i as f32 * 2.0 * std::f32::consts::PI * freq / 24000.0).sin())
+            .collect()
+    }
+
+    fn stretch(samples: Vec<f32>, speed: f64) -> Vec<f32> {
+        let mut stretch = TimeStretch::new(samples, 1, 24000);
+        let mut out = vec![];
+        while let Some(hop) = stretch.next_hop(speed) {
+            out.extend(hop);
+        }
+        out
+    }
+
+    // Upward zero crossings per second
+    fn frequency(samples: &[f32]) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        crossings as f32 * 24000.0 / samples.len() as f32
+    }
+
+    #[test]
+    fn ts_time_stretch() {
+        let input = tone(220.0, 1000);
+
+        // Unchanged at normal speed, but for padding at the end
+        let out = stretch(input.clone(), 1.0);
+        assert!(out.len() >= input.len() && out.len() < input.len() + 360);
+        assert!(input
+            .iter()
+            .zip(out.iter())
+            .all(|(a, b)| (a - b).abs() < 1e-4));
+
+        // Shorter and longer, same pitch
+        for speed in [2.0, 0.5] {
+            let out = stretch(input.clone(), speed);
+            let expected = input.len() as f64 / speed;
+            assert!((out.len() as f64 - expected).abs() < 720.0);
+            assert!((frequency(&out[..out.len() - 720]) - 220.0).abs() < 5.0);
+        }
+
+        // Out of range speeds are clamped
+        assert_eq!(
+            stretch(input.clone(), 100.0).len(),
+            stretch(input, SPEED_MAX).len()
+        );
+    }
+
+    #[test]
+    fn ts_time_stretch_stereo() {
+        let input: Vec<f32> = tone(220.0, 500).iter().flat_map(|s| [*s, -*s]).collect();
+
+        let mut stretch = TimeStretch::new(input, 2, 24000);
+        let mut out = vec![];
+        // Speed changed halfway
+        while let Some(hop) = stretch.next_hop(if out.len() < 12000 { 1.0 } else { 2.0 }) {
+            out.extend(hop);
+        }
+
+        assert!(out.chunks_exact(2).all(|f| (f[0] + f[1]).abs() < 1e-4));
+        assert!(out.len() > 18000 && out.len() < 19000);
+    }
+}
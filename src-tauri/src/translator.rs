@@ -0,0 +1,361 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use crate::host;
+use crate::language;
+use crate::scheduler;
+use crate::voicegen_agent;
+use crate::voicegen_filter;
+
+const CACHE_LENGTH: usize = 512;
+// A server that does not answer in time is given up, and the text read as it is
+const TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranslateSettings {
+    pub enabled: bool,
+    // LibreTranslate compatible server
+    pub url: String,
+    pub api_key: Option<String>,
+    pub target: String,
+}
+
+impl TranslateSettings {
+    pub fn new() -> Self {
+        TranslateSettings {
+            enabled: false,
+            url: "http://127.0.0.1:5000".to_string(),
+            api_key: None,
+            target: "ja".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Job {
+    pub source: String,
+    pub target: String,
+    pub url: String,
+    pub api_key: Option<String>,
+    // Voice reading the original text should the translation fail
+    pub source_addr: std::net::SocketAddr,
+    pub source_speaker: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Translation {
+    tweet_id: String,
+    text: String,
+}
+
+#[derive(Debug)]
+pub enum TranslateError {
+    Unknown(String),
+}
+
+pub type TranslateResult<'a> = Pin<Box<dyn Future<Output = Result<String, TranslateError>> + Send + 'a>>;
+
+pub trait Translator {
+    fn translate<'a>(&'a self, text: &'a str, source: &'a str, target: &'a str) -> TranslateResult<'a>;
+}
+
+pub struct LibreTranslate {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl LibreTranslate {
+    pub fn new(url: &str, api_key: Option<String>) -> Self {
+        LibreTranslate {
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+            url: url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+impl Translator for LibreTranslate {
+    fn translate<'a>(&'a self, text: &'a str, source: &'a str, target: &'a str) -> TranslateResult<'a> {
+        Box::pin(async move {
+            let url: String = format!("{}/translate", self.url);
+
+            let mut body = serde_json::json!({
+                "q": text,
+                "source": source,
+                "target": target,
+                "format": "text",
+            });
+            if let Some(ref key) = self.api_key {
+                body["api_key"] = serde_json::json!(key);
+            }
+
+            let resp = self
+                .client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+                .await
+                .map_err(|e| TranslateError::Unknown(e.to_string()))?
+                .text()
+                .await
+                .map_err(|e| TranslateError::Unknown(e.to_string()))?;
+
+            let resp: serde_json::Value = serde_json::from_str(resp.as_str())
+                .map_err(|e| TranslateError::Unknown(e.to_string()))?;
+
+            resp["translatedText"]
+                .as_str()
+                .map(|s| s.to_string())
+                .ok_or_else(|| TranslateError::Unknown(resp.to_string()))
+        })
+    }
+}
+
+// Translations by tweet id, languages and text, as headlines are translated apart
+struct Cache {
+    map: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+fn cache_key(tweet_id: &str, job: &Job, text: &str) -> String {
+    format!(
+        "{}/{}/{}/{:016x}",
+        tweet_id,
+        job.source,
+        job.target,
+        scheduler::stable_hash(text)
+    )
+}
+
+impl Cache {
+    fn new() -> Self {
+        Cache {
+            map: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&String> {
+        self.map.get(key)
+    }
+
+    fn insert(&mut self, key: String, text: String) {
+        if self.map.insert(key.clone(), text).is_none() {
+            self.order.push_back(key);
+        }
+
+        while self.order.len() > CACHE_LENGTH {
+            let id = self.order.pop_front().unwrap();
+            self.map.remove(&id);
+        }
+    }
+}
+
+// The translation of the playbook's text, which is then read instead.
+// Should it fail, the original text is read in the voice of its language,
+// and not kept for replay so that the translation is tried again.
+async fn translate<T: Translator + Sync>(
+    translator: &T,
+    cache: &Mutex<Cache>,
+    job: Job,
+    msg: &mut voicegen_agent::Playbook,
+) -> Option<String> {
+    let key = cache_key(msg.tweet_id.as_str(), &job, msg.text.as_str());
+    let cached = cache.lock().unwrap().get(&key).cloned();
+
+    let result = match cached {
+        Some(text) => Ok(text),
+        None => translator
+            .translate(msg.text.as_str(), job.source.as_str(), job.target.as_str())
+            .await,
+    };
+
+    match result {
+        Ok(text) => {
+            cache.lock().unwrap().insert(key, text.clone());
+            msg.text = text.clone();
+            Some(text)
+        }
+
+        Err(TranslateError::Unknown(emsg)) => {
+            println!("translator: failed to translate {:?}", emsg);
+
+            if job.source != language::JAPANESE {
+                msg.emoji.mode = voicegen_filter::EmojiMode::Drop;
+            }
+            msg.lang = job.source;
+            msg.addr = job.source_addr;
+            msg.speaker = job.source_speaker;
+            msg.cacheable = false;
+            None
+        }
+    }
+}
+
+// Each translation runs in a task of its own, so that the other tweets
+// are synthesized meanwhile
+pub fn start(
+    host: host::Host,
+    mut playbook_rx: tokio::sync::mpsc::Receiver<voicegen_agent::Playbook>,
+    playbook_tx: tokio::sync::mpsc::Sender<voicegen_agent::Playbook>,
+) {
+    tokio::spawn(async move {
+        let cache = Arc::new(Mutex::new(Cache::new()));
+
+        loop {
+            let mut msg = match playbook_rx.recv().await {
+                Some(msg) => msg,
+                None => {
+                    println!("translator: exit");
+                    return;
+                }
+            };
+
+            if msg.cancel.is_cancelled() {
+                continue;
+            }
+
+            let job = match msg.translation.take() {
+                Some(job) => job,
+                None => {
+                    if playbook_tx.send(msg).await.is_err() {
+                        println!("translator: voicegen_agent closes pci");
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            let host = host.clone();
+            let cache = cache.clone();
+            let playbook_tx = playbook_tx.clone();
+            tokio::spawn(async move {
+                let translator = LibreTranslate::new(job.url.as_str(), job.api_key.clone());
+                let cancel = msg.cancel.clone();
+                let translated = tokio::select! {
+                    translated = translate(&translator, &cache, job, &mut msg) => translated,
+                    _ = cancel.cancelled() => return,
+                };
+
+                if let Some(text) = translated {
+                    host.emit(
+                        "tauri://frontend/display/translation",
+                        Translation {
+                            tweet_id: msg.tweet_id.clone(),
+                            text,
+                        },
+                    );
+                }
+
+                if playbook_tx.send(msg).await.is_err() {
+                    println!("translator: voicegen_agent closes pci");
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Answers with the text given, or fails without one
+    struct Fixed(Option<String>);
+
+    impl Translator for Fixed {
+        fn translate<'a>(&'a self, _: &'a str, _: &'a str, _: &'a str) -> TranslateResult<'a> {
+            Box::pin(async move {
+                self.0
+                    .clone()
+                    .ok_or_else(|| TranslateError::Unknown("unreachable".to_string()))
+            })
+        }
+    }
+
+    fn job(source: &str, target: &str) -> Job {
+        Job {
+            source: source.to_string(),
+            target: target.to_string(),
+            url: "http://127.0.0.1:5000".to_string(),
+            api_key: None,
+            source_addr: std::net::SocketAddr::from(([127, 0, 0, 1], 50021)),
+            source_speaker: 1,
+        }
+    }
+
+    fn playbook(text: &str) -> voicegen_agent::Playbook {
+        let record = scheduler::Record {
+            tweet_id: "1".to_string(),
+            author_id: "100".to_string(),
+            created_at: "".to_string(),
+            text: text.to_string(),
+            name: "name".to_string(),
+            username: "user".to_string(),
+            profile_image_url: "".to_string(),
+            attachments: vec![],
+            lang: "en".to_string(),
+            is_retweet: false,
+            is_reply: false,
+            priority: false,
+        };
+
+        voicegen_agent::into(
+            record,
+            std::net::SocketAddr::from(([127, 0, 0, 1], 50031)),
+            2,
+            1.0,
+            voicegen_filter::EmojiSettings::new(),
+            "ja".to_string(),
+        )
+    }
+
+    #[test]
+    fn ts_cache_key() {
+        let key = cache_key("1", &job("en", "ja"), "Hello");
+
+        assert_eq!(key, cache_key("1", &job("en", "ja"), "Hello"));
+        // Headlines are translated apart from the full text
+        assert_ne!(key, cache_key("1", &job("en", "ja"), "Hel"));
+        assert_ne!(key, cache_key("1", &job("en", "ko"), "Hello"));
+        assert_ne!(key, cache_key("1", &job("fr", "ja"), "Hello"));
+        assert_ne!(key, cache_key("2", &job("en", "ja"), "Hello"));
+    }
+
+    #[tokio::test]
+    async fn ts_translate_cached() {
+        let cache = Mutex::new(Cache::new());
+
+        let mut msg = playbook("Hello");
+        let translated = translate(&Fixed(Some("こんにちは".to_string())), &cache, job("en", "ja"), &mut msg).await;
+        assert_eq!(translated, Some("こんにちは".to_string()));
+        assert_eq!((msg.text.as_str(), msg.speaker, msg.cacheable), ("こんにちは", 2, true));
+
+        // Served from the cache while the server is down
+        let mut msg = playbook("Hello");
+        let translated = translate(&Fixed(None), &cache, job("en", "ja"), &mut msg).await;
+        assert_eq!(translated, Some("こんにちは".to_string()));
+    }
+
+    #[tokio::test]
+    async fn ts_translate_failed() {
+        let cache = Mutex::new(Cache::new());
+
+        let mut msg = playbook("Hello");
+        assert_eq!(translate(&Fixed(None), &cache, job("en", "ja"), &mut msg).await, None);
+
+        // The original text in the voice of its language, not kept for replay
+        assert_eq!(msg.text, "Hello");
+        assert_eq!((msg.lang.as_str(), msg.speaker), ("en", 1));
+        assert_eq!(msg.addr, std::net::SocketAddr::from(([127, 0, 0, 1], 50021)));
+        assert_eq!(msg.emoji.mode, voicegen_filter::EmojiMode::Drop);
+        assert!(!msg.cacheable);
+    }
+}
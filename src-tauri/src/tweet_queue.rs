@@ -0,0 +1,467 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound::{Excluded, Included, Unbounded};
+
+use crate::scheduler;
+use crate::voicegen_agent;
+
+// Distance between positions given to new items
+const GAP: i64 = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Waiting,
+    Synthesizing,
+    Ready,
+    Played,
+}
+
+impl State {
+    fn index(self) -> usize {
+        match self {
+            State::Waiting => 0,
+            State::Synthesizing => 1,
+            State::Ready => 2,
+            State::Played => 3,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Item {
+    pub record: scheduler::Record,
+    pub state: State,
+    pub speech: Option<voicegen_agent::Speech>,
+    pos: i64,
+}
+
+// Tweets of a timeline in reading order, keyed by tweet id.
+// Played tweets come first; the cursor is the position of the last played one.
+// Lookups are O(1) and insertions, removals and moves are O(log n).
+pub struct TweetQueue {
+    items: HashMap<String, Item>,
+    order: BTreeMap<i64, String>,
+    cursor: Option<i64>,
+    counts: [usize; 4],
+}
+
+impl TweetQueue {
+    pub fn new() -> Self {
+        TweetQueue {
+            items: HashMap::new(),
+            order: BTreeMap::new(),
+            cursor: None,
+            counts: [0; 4],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn count(&self, state: State) -> usize {
+        self.counts[state.index()]
+    }
+
+    pub fn get(&self, twid: &str) -> Option<&Item> {
+        self.items.get(twid)
+    }
+
+    pub fn state(&self, twid: &str) -> Option<State> {
+        self.items.get(twid).map(|item| item.state)
+    }
+
+    pub fn queued(&self) -> impl Iterator<Item = &Item> + '_ {
+        let lower = match self.cursor {
+            Some(c) => Excluded(c),
+            None => Unbounded,
+        };
+
+        self.order
+            .range((lower, Unbounded))
+            .map(move |(_, id)| &self.items[id])
+    }
+
+    pub fn played(&self) -> impl Iterator<Item = &Item> + '_ {
+        // Nothing is below i64::MIN when nothing has been played
+        let upper = match self.cursor {
+            Some(c) => Included(c),
+            None => Excluded(i64::MIN),
+        };
+
+        self.order
+            .range((Unbounded, upper))
+            .map(move |(_, id)| &self.items[id])
+    }
+
+    pub fn first_queued(&self) -> Option<&Item> {
+        self.queued().next()
+    }
+
+    pub fn last_played(&self) -> Option<&Item> {
+        self.cursor.map(|c| &self.items[&self.order[&c]])
+    }
+
+    pub fn push_back(&mut self, record: scheduler::Record) -> bool {
+        let last = self.order.keys().next_back().copied();
+        self.insert_after(last, record, State::Waiting)
+    }
+
+    pub fn push_front(&mut self, record: scheduler::Record) -> bool {
+        self.insert_after(self.cursor, record, State::Waiting)
+    }
+
+    // Queue after the leading queued items matching f
+    pub fn insert_after_leading<F: Fn(&scheduler::Record) -> bool>(
+        &mut self,
+        record: scheduler::Record,
+        f: F,
+    ) -> bool {
+        let prev = self
+            .queued()
+            .take_while(|item| f(&item.record))
+            .last()
+            .map(|item| item.pos)
+            .or(self.cursor);
+
+        self.insert_after(prev, record, State::Waiting)
+    }
+
+    // Append to the history without reading it
+    pub fn push_played(&mut self, record: scheduler::Record) -> bool {
+        let twid = record.tweet_id.clone();
+        if !self.insert_after(self.cursor, record, State::Played) {
+            return false;
+        }
+
+        self.cursor = Some(self.items[&twid].pos);
+        true
+    }
+
+    pub fn take(&mut self, twid: &str) -> Option<Item> {
+        let item = self.items.remove(twid)?;
+        self.order.remove(&item.pos);
+        self.counts[item.state.index()] -= 1;
+
+        if self.cursor == Some(item.pos) {
+            self.cursor = self.order.range(..item.pos).next_back().map(|(k, _)| *k);
+        }
+
+        Some(item)
+    }
+
+    pub fn set_state(&mut self, twid: &str, state: State) {
+        if let Some(item) = self.items.get_mut(twid) {
+            self.counts[item.state.index()] -= 1;
+            self.counts[state.index()] += 1;
+            item.state = state;
+
+            if state != State::Ready {
+                item.speech = None;
+            }
+        }
+    }
+
+    pub fn set_speech(&mut self, speech: voicegen_agent::Speech) -> bool {
+        let twid = speech.tweet_id.clone();
+        match self.state(&twid) {
+            Some(State::Waiting) | Some(State::Synthesizing) => {
+                self.set_state(&twid, State::Ready);
+                self.items.get_mut(&twid).unwrap().speech = Some(speech);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Mark every queued item up to and including twid as played
+    pub fn play_through(&mut self, twid: &str) -> bool {
+        let pos = match self.items.get(twid) {
+            Some(item) if item.state != State::Played => item.pos,
+            _ => return false,
+        };
+
+        let lower = match self.cursor {
+            Some(c) => Excluded(c),
+            None => Unbounded,
+        };
+        let ids: Vec<String> = self
+            .order
+            .range((lower, Included(pos)))
+            .map(|(_, id)| id.clone())
+            .collect();
+
+        for id in ids {
+            self.set_state(&id, State::Played);
+        }
+        self.cursor = Some(pos);
+
+        true
+    }
+
+    // Queue the history again from twid
+    pub fn rewind_to(&mut self, twid: &str) -> bool {
+        let pos = match self.items.get(twid) {
+            Some(item) if item.state == State::Played => item.pos,
+            _ => return false,
+        };
+
+        let ids: Vec<String> = self
+            .order
+            .range((Included(pos), Included(self.cursor.unwrap())))
+            .map(|(_, id)| id.clone())
+            .collect();
+
+        for id in ids {
+            self.set_state(&id, State::Waiting);
+        }
+        self.cursor = self.order.range(..pos).next_back().map(|(k, _)| *k);
+
+        true
+    }
+
+    // Move a queued or played item to the front of the queue
+    pub fn move_to_front(&mut self, twid: &str) -> bool {
+        let mut item = match self.take(twid) {
+            Some(item) => item,
+            None => return false,
+        };
+
+        if item.state == State::Played {
+            item.state = State::Waiting;
+        }
+
+        self.put_front(item);
+        true
+    }
+
+    // Put an item taken out of the queue back at the front, keeping its state
+    pub fn put_front(&mut self, item: Item) {
+        let pos = self.slot_after(self.cursor);
+        self.put(pos, item);
+    }
+
+    // Every synthesized item has to be synthesized again
+    pub fn reset_ready(&mut self) {
+        let ids: Vec<String> = self
+            .queued()
+            .filter(|item| item.state == State::Ready)
+            .map(|item| item.record.tweet_id.clone())
+            .collect();
+
+        for id in ids {
+            self.set_state(&id, State::Waiting);
+        }
+    }
+
+    // Items being synthesized without a job any more are waiting again
+    pub fn release<F: Fn(&str) -> bool>(&mut self, has_job: F) {
+        let ids: Vec<String> = self
+            .queued()
+            .filter(|item| item.state == State::Synthesizing && !has_job(&item.record.tweet_id))
+            .map(|item| item.record.tweet_id.clone())
+            .collect();
+
+        for id in ids {
+            self.set_state(&id, State::Waiting);
+        }
+    }
+
+    // Drop the oldest history beyond max; returns the dropped ids
+    pub fn trim_history(&mut self, max: usize) -> Vec<String> {
+        let mut drop_list = vec![];
+        while self.count(State::Played) > max {
+            let id = self.order.values().next().unwrap().clone();
+            self.take(&id);
+            drop_list.push(id);
+        }
+
+        drop_list
+    }
+
+    pub fn clear_queued(&mut self) -> Vec<String> {
+        let ids: Vec<String> = self.queued().map(|item| item.record.tweet_id.clone()).collect();
+        for id in ids.iter() {
+            self.take(id);
+        }
+
+        ids
+    }
+
+    pub fn clear(&mut self) -> Vec<String> {
+        let ids: Vec<String> = self.order.values().cloned().collect();
+        *self = TweetQueue::new();
+
+        ids
+    }
+
+    fn insert_after(&mut self, prev: Option<i64>, record: scheduler::Record, state: State) -> bool {
+        if self.items.contains_key(&record.tweet_id) {
+            return false;
+        }
+
+        let pos = self.slot_after(prev);
+        self.put(
+            pos,
+            Item {
+                record,
+                state,
+                speech: None,
+                pos,
+            },
+        );
+        true
+    }
+
+    fn put(&mut self, pos: i64, mut item: Item) {
+        item.pos = pos;
+        self.counts[item.state.index()] += 1;
+        self.order.insert(pos, item.record.tweet_id.clone());
+        self.items.insert(item.record.tweet_id.clone(), item);
+    }
+
+    // Free position right after prev, or at the very front when prev is None
+    fn slot_after(&mut self, prev: Option<i64>) -> i64 {
+        let next = match prev {
+            Some(p) => self.order.range((Excluded(p), Unbounded)).next(),
+            None => self.order.iter().next(),
+        }
+        .map(|(k, _)| *k);
+
+        match (prev, next) {
+            (None, None) => 0,
+            (Some(p), None) => p + GAP,
+            (None, Some(n)) => n - GAP,
+            (Some(p), Some(n)) if n - p >= 2 => p + (n - p) / 2,
+            (Some(p), Some(_)) => {
+                let id = self.order[&p].clone();
+                self.renumber();
+                self.slot_after(Some(self.items[&id].pos))
+            }
+        }
+    }
+
+    // Spread positions evenly when there is no room left between two items
+    fn renumber(&mut self) {
+        let cursor_id = self.cursor.map(|c| self.order[&c].clone());
+        let ids: Vec<String> = self.order.values().cloned().collect();
+
+        self.order.clear();
+        for (i, id) in ids.into_iter().enumerate() {
+            let pos = i as i64 * GAP;
+            self.items.get_mut(&id).unwrap().pos = pos;
+            self.order.insert(pos, id);
+        }
+
+        self.cursor = cursor_id.map(|id| self.items[&id].pos);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(twid: &str) -> scheduler::Record {
+        scheduler::Record {
+            tweet_id: twid.to_string(),
+            author_id: "100".to_string(),
+            created_at: "".to_string(),
+            text: "text".to_string(),
+            name: "name".to_string(),
+            username: "user".to_string(),
+            profile_image_url: "".to_string(),
+            attachments: vec![],
+            lang: "ja".to_string(),
+            is_retweet: false,
+            is_reply: false,
+            priority: false,
+        }
+    }
+
+    fn speech(twid: &str) -> voicegen_agent::Speech {
+        voicegen_agent::Speech {
+            tweet_id: twid.to_string(),
+            job_id: 0,
+            addr: std::net::SocketAddr::from(([127, 0, 0, 1], 50021)),
+            text: vec![1],
+            name: vec![],
+            cacheable: true,
+        }
+    }
+
+    fn queued(queue: &TweetQueue) -> Vec<&str> {
+        queue.queued().map(|item| item.record.tweet_id.as_str()).collect()
+    }
+
+    fn played(queue: &TweetQueue) -> Vec<&str> {
+        queue.played().map(|item| item.record.tweet_id.as_str()).collect()
+    }
+
+    #[test]
+    fn ts_order_and_state() {
+        let mut queue = TweetQueue::new();
+        for id in ["1", "2", "3"] {
+            assert!(queue.push_back(record(id)));
+        }
+        assert!(!queue.push_back(record("2")));
+        assert!(queue.push_front(record("0")));
+        assert!(queue.push_played(record("muted")));
+
+        assert_eq!(played(&queue), vec!["muted"]);
+        assert_eq!(queued(&queue), vec!["0", "1", "2", "3"]);
+
+        assert!(queue.set_speech(speech("2")));
+        assert_eq!(queue.state("2"), Some(State::Ready));
+        assert_eq!(queue.count(State::Ready), 1);
+
+        assert!(queue.play_through("2"));
+        assert_eq!(played(&queue), vec!["muted", "0", "1", "2"]);
+        assert!(queue.get("2").unwrap().speech.is_none());
+        assert_eq!(queue.count(State::Ready), 0);
+        assert_eq!(queue.count(State::Played), 4);
+
+        assert!(queue.rewind_to("1"));
+        assert_eq!(played(&queue), vec!["muted", "0"]);
+        assert_eq!(queued(&queue), vec!["1", "2", "3"]);
+
+        assert_eq!(queue.trim_history(1), vec!["muted".to_string()]);
+        assert_eq!(queue.clear_queued().len(), 3);
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn ts_insert_without_gap() {
+        let mut queue = TweetQueue::new();
+        queue.push_back(record("a"));
+        queue.push_back(record("z"));
+
+        // Keep inserting right after "a" until positions run out
+        for i in 0..40 {
+            let id = format!("{}", i);
+            queue.insert_after_leading(record(&id), |r| r.tweet_id == "a");
+        }
+
+        let ids = queued(&queue);
+        assert_eq!(ids.len(), 42);
+        assert_eq!(ids[0], "a");
+        assert_eq!(ids[1], "39");
+        assert_eq!(ids[41], "z");
+    }
+
+    #[test]
+    fn ts_take_cursor() {
+        let mut queue = TweetQueue::new();
+        for id in ["1", "2", "3"] {
+            queue.push_back(record(id));
+        }
+        queue.play_through("2");
+
+        // Taking the last played item moves the cursor back
+        assert!(queue.take("2").is_some());
+        assert_eq!(queue.last_played().unwrap().record.tweet_id, "1");
+
+        assert!(queue.move_to_front("1"));
+        assert_eq!(played(&queue), Vec::<&str>::new());
+        assert_eq!(queued(&queue), vec!["1", "3"]);
+        assert_eq!(queue.state("1"), Some(State::Waiting));
+    }
+}
@@ -1,12 +1,11 @@
 use twitter_v2::authorization::Oauth2Token;
 use serde::{Deserialize, Serialize};
 
+use crate::host;
 use crate::scheduler;
 use crate::twitter_authorizator;
 use crate::twitter_client;
 
-use tauri::Manager;
-
 //const QUEUE_LENGTH : usize = 24;
 //const QUEUE_LENGTH: usize = 512;
 const QUEUE_LENGTH: usize = 64;
@@ -19,32 +18,26 @@ pub enum Timeline {
 }
 
 fn emit_clear_error(
-        app_handle: &tauri::AppHandle,
+        host: &host::Host,
     ) {
 
-    app_handle
-        .emit_all("tauri://frontend/authorization-failed", "")
-        .unwrap();
+    host.emit("tauri://frontend/authorization-failed", "");
 
-    app_handle
-        .emit_all("tauri://frontend/other-error", "")
-        .unwrap();
+    host.emit("tauri://frontend/other-error", "");
 }
 
 fn emit_error_other(
-        app_handle: &tauri::AppHandle,
+        host: &host::Host,
     ) {
 
-    app_handle
-        .emit_all(
-            "tauri://frontend/other-error",
-            "ネットワークに異常があります",
-            )
-        .unwrap();
+    host.emit(
+        "tauri://frontend/other-error",
+        "ネットワークに異常があります",
+    );
 }
 
 async fn request_search_timeline(
-                        app_handle: &tauri::AppHandle,
+                        host: &host::Host,
                         ctx: &mut SearchTimelineContext,
                         token_opt: &mut Option<Oauth2Token>,
                         ) -> Vec<scheduler::Record>
@@ -56,7 +49,7 @@ async fn request_search_timeline(
                                                 ctx.query_opt.as_ref().unwrap().as_str(),
                                                 ctx.since_id_opt.as_ref().map(|s| s.as_str())).await {
             Ok(t) => {
-                emit_clear_error(&app_handle);
+                emit_clear_error(host);
                 t
             }
 
@@ -71,7 +64,7 @@ async fn request_search_timeline(
                 twitter_client::RequestError::Unknown(msg) => {
                     println!("twitter_agent: unknown {:?}", msg);
 
-                    emit_error_other(&app_handle);
+                    emit_error_other(host);
 
                     return vec![];
                 }
@@ -99,7 +92,7 @@ async fn request_search_timeline(
 }
 
 async fn request_user_timeline(
-                        app_handle: &tauri::AppHandle,
+                        host: &host::Host,
                         usrctx: &mut UserTimelineContext,
                         token_opt: &mut Option<Oauth2Token>,
                         ) -> Vec<scheduler::Record>
@@ -122,7 +115,7 @@ async fn request_user_timeline(
                 twitter_client::RequestError::Unknown(msg) => {
                     println!("twitter_agent: user id error unknown  {:?}", msg);
 
-                    emit_error_other(&app_handle);
+                    emit_error_other(host);
                     return vec![];
                 }
             }
@@ -137,7 +130,7 @@ async fn request_user_timeline(
             match twitter_client::request_tweet_new(&token_opt.clone().unwrap(), usrctx.user_id_opt.clone().unwrap().as_str(), usrctx.since_id_opt.as_ref().map(|s| s.as_str())).await {
                 //let tweets = match twitter_client::request_user_timeline(&token, user_id.as_str(), start_time).await {
                 Ok(t) => {
-                    emit_clear_error(&app_handle);
+                    emit_clear_error(host);
                     t
                 }
 
@@ -152,7 +145,7 @@ async fn request_user_timeline(
                     twitter_client::RequestError::Unknown(msg) => {
                         println!("twitter_agent: unknown {:?}", msg);
 
-                        emit_error_other(&app_handle);
+                        emit_error_other(host);
 
                         return vec![];
                     }
@@ -210,7 +203,7 @@ impl UserTimelineContext {
 
 
 pub fn start(
-    app_handle: tauri::AppHandle,
+    host: host::Host,
     authctl_tx: tokio::sync::mpsc::Sender<twitter_authorizator::AuthControl>,
     mut token_rx: tokio::sync::mpsc::Receiver<Oauth2Token>,
     mut timeline_rx: tokio::sync::mpsc::Receiver<Timeline>,
@@ -250,7 +243,7 @@ pub fn start(
 
                     let records = match timeline {
                         Timeline::User => {
-                            request_user_timeline(&app_handle, &mut usrctx, &mut token_opt).await
+                            request_user_timeline(&host, &mut usrctx, &mut token_opt).await
                         }
 
                         Timeline::Search{ref query} => {
@@ -261,7 +254,7 @@ pub fn start(
                                 search_ctx.query_opt = Some(query.clone());
                             }
 
-                            request_search_timeline(&app_handle, &mut search_ctx, &mut token_opt).await
+                            request_search_timeline(&host, &mut search_ctx, &mut token_opt).await
                         }
                     };
 
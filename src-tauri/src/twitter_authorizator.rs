@@ -20,7 +20,7 @@ use oauth2::{
 use twitter_v2::authorization::Oauth2Token;
 use twitter_v2::error::Result;
 
-use tauri::Manager;
+use crate::host;
 
 fn callback_server() -> SocketAddr {
     SocketAddr::from(([127, 0, 0, 1], 41157))
@@ -232,59 +232,24 @@ pub fn start_server() -> (
 
 async fn perform_oauth2_flow() -> Oauth2Token {
     let (shutdown_tx, token_rx) = start_server();
-    webbrowser::open(entrypoint_url().as_str()).unwrap();
+
+    // No browser on a headless box; the url can be opened elsewhere
+    if webbrowser::open(entrypoint_url().as_str()).is_err() {
+        println!("twitter_authorizator: open {} to authorize", entrypoint_url());
+    }
 
     let t = token_rx.await.ok().unwrap();
     shutdown_tx.send(()).ok().unwrap();
     t
 }
 
-async fn get_token_from_storage(app_handle: &tauri::AppHandle) -> Option<Oauth2Token> {
-    println!("get token from storage");
-    app_handle
-        .emit_all("tauri://frontend/token-request", ())
-        .unwrap();
-
-    let (token_complete_tx, token_complete_rx) =
-        tokio::sync::oneshot::channel::<Option<Oauth2Token>>();
-    let token_complete_tx: std::sync::Mutex<
-        Option<tokio::sync::oneshot::Sender<Option<Oauth2Token>>>,
-    > = std::sync::Mutex::new(Some(token_complete_tx));
-
-    let id = app_handle.listen_global("tauri://backend/token-response", move |event| {
-        let t: Option<Oauth2Token> = {
-            if let Some(payload) = event.payload() {
-                serde_json::from_str(payload).unwrap()
-            } else {
-                None
-            }
-        };
-
-        let mut token_complete_tx = token_complete_tx.lock().unwrap();
-        token_complete_tx.take().unwrap().send(t).unwrap();
-    });
-
-    let t: Option<Oauth2Token> = token_complete_rx.await.unwrap();
-    // unlisten to the event using the `id` returned on the `listen_global` function
-    // an `once_global` API is also exposed on the `App` struct
-    app_handle.unlisten(id);
-
-    t
-}
-
-fn save_token_into_storage(app_handle: &tauri::AppHandle, token: Oauth2Token) {
-    app_handle
-        .emit_all("tauri://frontend/token-register", token)
-        .unwrap();
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AuthControl {
     Authorize,
 }
 
 pub fn start(
-    app_handle: tauri::AppHandle,
+    host: host::Host,
     mut authctl_rx: tokio::sync::mpsc::Receiver<AuthControl>,
 ) -> tokio::sync::mpsc::Receiver<Oauth2Token> {
     let (token_tx, token_rx) = tokio::sync::mpsc::channel::<Oauth2Token>(1);
@@ -295,7 +260,7 @@ pub fn start(
                 Some(msg) => match msg {
                     AuthControl::Authorize => {
                         let mut token: Oauth2Token = {
-                            if let Some(t) = get_token_from_storage(&app_handle).await {
+                            if let Some(t) = host.load_token().await {
                                 println!("token is already in storage");
 
                                 t
@@ -303,7 +268,7 @@ pub fn start(
                                 println!("Token not found. Request authorization");
 
                                 let t = perform_oauth2_flow().await;
-                                save_token_into_storage(&app_handle, t.clone());
+                                host.save_token(&t);
                                 t
                             }
                         };
@@ -311,7 +276,7 @@ pub fn start(
                         if let Ok(expired) = refresh_token_if_expired(&mut token).await{
                             if expired {
                                 println!("******* Token refreshed *********");
-                                save_token_into_storage(&app_handle, token.clone());
+                                host.save_token(&token);
 
                             } else {
                                 println!("token is not expired");
@@ -319,9 +284,7 @@ pub fn start(
                             }
                         } else {
                             // Failed to exchanging refresh token
-                            app_handle
-                                .emit_all("tauri://frontend/token-unregister", ())
-                                .unwrap();
+                            host.remove_token();
 
                             ()
                         }
@@ -107,7 +107,7 @@ pub async fn request_tweet_new(
     let mut query = [
         ("expansions", "author_id,attachments.media_keys"),
         ("user.fields", "profile_image_url"),
-        ("tweet.fields", "created_at"),
+        ("tweet.fields", "created_at,lang,referenced_tweets"),
         ("media.fields", "preview_image_url,type,url"),
         ("max_results", "25"),
     ]
@@ -164,7 +164,7 @@ pub async fn request_search(
         ("query", query),
         ("expansions", "author_id,attachments.media_keys"),
         ("user.fields", "profile_image_url"),
-        ("tweet.fields", "created_at"),
+        ("tweet.fields", "created_at,lang,referenced_tweets"),
         ("media.fields", "preview_image_url,type,url"),
         ("max_results", "25"),
     ]
@@ -0,0 +1,447 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use wana_kana::to_katakana::*;
+
+use crate::storage;
+
+pub const FILE_NAME: &str = "user_dict.json";
+// Surfaces registered into each engine by sync, so that only those are ever deleted from it
+const SYNCED_FILE_NAME: &str = "user_dict_synced.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Word {
+    pub surface: String,
+    pub reading: String,
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub priority: u32,
+}
+
+#[derive(Debug)]
+pub enum DictError {
+    InvalidRegex(String),
+    Storage(String),
+}
+
+impl std::fmt::Display for DictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DictError::InvalidRegex(e) => write!(f, "invalid regex: {}", e),
+            DictError::Storage(e) => write!(f, "failed to save dictionary: {}", e),
+        }
+    }
+}
+
+struct Entry {
+    word: Word,
+    re: Option<Regex>,
+}
+
+impl Entry {
+    fn new(word: Word) -> Result<Self, DictError> {
+        let re = if word.regex {
+            Some(
+                Regex::new(word.surface.as_str())
+                    .map_err(|e| DictError::InvalidRegex(e.to_string()))?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Entry { word, re })
+    }
+}
+
+// A change to the dictionary of an engine
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncOp {
+    Add(Word),
+    // By uuid of the engine's word
+    Update(String, Word),
+    // Uuid and surface
+    Delete(String, String),
+}
+
+// Engines store surfaces in full width
+fn engine_surface(surface: &str) -> String {
+    surface
+        .chars()
+        .map(|c| match c {
+            '!'..='~' => char::from_u32(c as u32 + 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+// Katakana as engines take it, from a reading in kana or romaji
+pub fn pronunciation(reading: &str) -> String {
+    to_katakana(reading)
+        .chars()
+        .map(|c| match c {
+            'ぁ'..='ゖ' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+// The reading with $1, ${1}, $name and ${name} filled in, as Captures::expand does
+fn expand(
+    reading: &str,
+    re: &Regex,
+    input: &str,
+    locs: &regex::CaptureLocations,
+    output: &mut String,
+) {
+    let mut rest = reading;
+    while let Some(i) = rest.find('$') {
+        output.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        if let Some(r) = rest.strip_prefix('$') {
+            output.push('$');
+            rest = r;
+            continue;
+        }
+
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(r) => match r.find('}') {
+                Some(j) => (&r[..j], &r[j + 1..]),
+                None => ("", rest),
+            },
+            None => {
+                let len = rest
+                    .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+                    .unwrap_or(rest.len());
+                (&rest[..len], &rest[len..])
+            }
+        };
+        if name.is_empty() {
+            output.push('$');
+            continue;
+        }
+
+        let index = match name.parse::<usize>() {
+            Ok(index) => Some(index),
+            Err(_) => re.capture_names().position(|n| n == Some(name)),
+        };
+        if let Some((start, end)) = index.and_then(|i| locs.get(i)) {
+            output.push_str(&input[start..end]);
+        }
+        rest = after;
+    }
+    output.push_str(rest);
+}
+
+pub struct UserDict {
+    path: Option<PathBuf>,
+    entries: Vec<Entry>,
+    // Engine address -> surfaces
+    synced: HashMap<String, Vec<String>>,
+}
+
+impl UserDict {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        UserDict {
+            path,
+            entries: vec![],
+            synced: HashMap::new(),
+        }
+    }
+
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let words: Vec<Word> = path
+            .as_ref()
+            .and_then(|p| storage::load(p.as_path()))
+            .unwrap_or_default();
+
+        let synced = path
+            .as_ref()
+            .and_then(|p| storage::load(p.with_file_name(SYNCED_FILE_NAME).as_path()))
+            .unwrap_or_default();
+
+        let mut dict = UserDict::new(path);
+        dict.synced = synced;
+        for word in words {
+            match Entry::new(word) {
+                Ok(entry) => dict.entries.push(entry),
+                Err(e) => println!("user_dict: skip entry {}", e),
+            }
+        }
+        dict.sort();
+
+        dict
+    }
+
+    fn save(&self) -> Result<(), DictError> {
+        match self.path {
+            Some(ref path) => storage::save(path.as_path(), &self.words())
+                .map_err(|e| DictError::Storage(e.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    // Higher priority first, then longer surface first
+    fn sort(&mut self) {
+        self.entries.sort_by(|a, b| {
+            b.word
+                .priority
+                .cmp(&a.word.priority)
+                .then(b.word.surface.chars().count().cmp(&a.word.surface.chars().count()))
+        });
+    }
+
+    pub fn words(&self) -> Vec<Word> {
+        self.entries.iter().map(|e| e.word.clone()).collect()
+    }
+
+    pub fn add(&mut self, word: Word) -> Result<(), DictError> {
+        let entry = Entry::new(word)?;
+
+        self.entries.retain(|e| e.word.surface != entry.word.surface);
+        self.entries.push(entry);
+        self.sort();
+
+        self.save()
+    }
+
+    pub fn remove(&mut self, surface: &str) -> Result<(), DictError> {
+        self.entries.retain(|e| e.word.surface != surface);
+
+        self.save()
+    }
+
+    // One pass over the input: at each position the match of the highest priority,
+    // then the longest, is replaced. Readings are never replaced again.
+    pub fn apply(&self, input: &str) -> String {
+        let mut output = String::with_capacity(input.len());
+        let mut pos = 0;
+
+        while let Some(c) = input[pos..].chars().next() {
+            let mut best: Option<(&Entry, usize, Option<regex::CaptureLocations>)> = None;
+            for entry in &self.entries {
+                let (end, locs) = match entry.re {
+                    // Captured in the whole input, so that ^, $ and \b keep their meaning
+                    Some(ref re) => {
+                        let mut locs = re.capture_locations();
+                        match re.captures_read_at(&mut locs, input, pos) {
+                            Some(m) if m.start() == pos && m.end() > pos => (m.end(), Some(locs)),
+                            _ => continue,
+                        }
+                    }
+                    None if !entry.word.surface.is_empty()
+                        && input[pos..].starts_with(entry.word.surface.as_str()) =>
+                    {
+                        (pos + entry.word.surface.len(), None)
+                    }
+                    None => continue,
+                };
+
+                let better = best.as_ref().map_or(true, |(b, b_end, _)| {
+                    (entry.word.priority, end) > (b.word.priority, *b_end)
+                });
+                if better {
+                    best = Some((entry, end, locs));
+                }
+            }
+
+            match best {
+                Some((entry, end, locs)) => {
+                    match (entry.re.as_ref(), locs) {
+                        (Some(re), Some(locs)) => {
+                            expand(entry.word.reading.as_str(), re, input, &locs, &mut output)
+                        }
+                        _ => output.push_str(entry.word.reading.as_str()),
+                    }
+                    pos = end;
+                }
+                None => {
+                    output.push(c);
+                    pos += c.len_utf8();
+                }
+            }
+        }
+
+        output
+    }
+
+    // Changes that bring the dictionary of an engine in line with this one.
+    // `registered` is the engine's dictionary, uuid -> word. Regex words are left out,
+    // and only words added by an earlier sync are deleted.
+    pub fn sync_ops(
+        &self,
+        addr: std::net::SocketAddr,
+        registered: &serde_json::Value,
+    ) -> Vec<SyncOp> {
+        let words: HashMap<String, &Word> = self
+            .entries
+            .iter()
+            .filter(|e| e.re.is_none())
+            .map(|e| (engine_surface(e.word.surface.as_str()), &e.word))
+            .collect();
+        let synced: Vec<String> = self
+            .synced
+            .get(&addr.to_string())
+            .map(|s| s.iter().map(|s| engine_surface(s)).collect())
+            .unwrap_or_default();
+
+        let mut ops = vec![];
+        let mut found = std::collections::HashSet::new();
+        for (uuid, registered) in registered.as_object().into_iter().flatten() {
+            let surface = registered["surface"].as_str().unwrap_or_default();
+            match words.get(surface) {
+                Some(word) => {
+                    found.insert(surface.to_string());
+                    let pronunciation = pronunciation(word.reading.as_str());
+                    if registered["pronunciation"] != pronunciation.as_str()
+                        || registered["priority"] != word.priority.min(10)
+                    {
+                        ops.push(SyncOp::Update(uuid.clone(), (*word).clone()));
+                    }
+                }
+                None if synced.iter().any(|s| s == surface) => {
+                    ops.push(SyncOp::Delete(uuid.clone(), surface.to_string()));
+                }
+                None => {}
+            }
+        }
+
+        // In dictionary order
+        for entry in self.entries.iter().filter(|e| e.re.is_none()) {
+            if !found.contains(&engine_surface(entry.word.surface.as_str())) {
+                ops.push(SyncOp::Add(entry.word.clone()));
+            }
+        }
+
+        ops
+    }
+
+    // Records the surfaces now in the engine by sync
+    pub fn set_synced(
+        &mut self,
+        addr: std::net::SocketAddr,
+        surfaces: Vec<String>,
+    ) -> Result<(), DictError> {
+        self.synced.insert(addr.to_string(), surfaces);
+
+        match self.path {
+            Some(ref path) => storage::save(
+                path.with_file_name(SYNCED_FILE_NAME).as_path(),
+                &self.synced,
+            )
+            .map_err(|e| DictError::Storage(e.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn word(surface: &str, reading: &str, regex: bool, priority: u32) -> Word {
+        Word {
+            surface: surface.to_string(),
+            reading: reading.to_string(),
+            regex,
+            priority,
+        }
+    }
+
+    #[test]
+    fn ts_apply_longest_first() {
+        let mut dict = UserDict::new(None);
+        dict.add(word("東京", "とうけい", false, 0)).unwrap();
+        dict.add(word("東京都", "とうきょうと", false, 0)).unwrap();
+
+        assert_eq!(dict.apply("東京都と東京"), "とうきょうとととうけい");
+    }
+
+    #[test]
+    fn ts_apply_priority() {
+        let mut dict = UserDict::new(None);
+        dict.add(word("twradio", "ついよみ", false, 0)).unwrap();
+        dict.add(word("tw", "ついった", false, 5)).unwrap();
+
+        assert_eq!(dict.apply("twradio"), "ついったradio");
+    }
+
+    #[test]
+    fn ts_apply_regex() {
+        let mut dict = UserDict::new(None);
+        dict.add(word(r"w{2,}$", "わら", true, 0)).unwrap();
+
+        assert_eq!(dict.apply("それなwww"), "それなわら");
+        assert!(dict.add(word("(", "", true, 0)).is_err());
+    }
+
+    #[test]
+    fn ts_apply_once() {
+        let mut dict = UserDict::new(None);
+        dict.add(word("AI", "エーアイ", false, 0)).unwrap();
+        dict.add(word("エー", "えええ", false, 0)).unwrap();
+        dict.add(word(r"(\d+)円", "${1}えん", true, 0)).unwrap();
+
+        // Readings are not replaced by other entries
+        assert_eq!(dict.apply("AIとエー"), "エーアイとえええ");
+        assert_eq!(dict.apply("100円"), "100えん");
+    }
+
+    #[test]
+    fn ts_apply_captures_in_context() {
+        let mut dict = UserDict::new(None);
+        // \B holds inside the input but not at the start of the match alone
+        dict.add(word(r"\B(\d)", "[$1]", true, 0)).unwrap();
+        dict.add(word(r"(?P<n>\d+)本", "${n}ほん$$", true, 1)).unwrap();
+
+        assert_eq!(dict.apply("a1"), "a[1]");
+        assert_eq!(dict.apply("3本"), "3ほん$");
+    }
+
+    #[test]
+    fn ts_pronunciation() {
+        assert_eq!(pronunciation("ついよみ"), "ツイヨミ");
+        assert_eq!(pronunciation("トウキョウ"), "トウキョウ");
+    }
+
+    #[test]
+    fn ts_sync_ops() {
+        let addr: std::net::SocketAddr = "127.0.0.1:50021".parse().unwrap();
+        let mut dict = UserDict::new(None);
+        dict.add(word("twradio", "ついよみ", false, 5)).unwrap();
+        dict.add(word("東京", "とうきょう", false, 0)).unwrap();
+        dict.add(word("新語", "しんご", false, 0)).unwrap();
+        dict.add(word("w+", "わら", true, 0)).unwrap();
+        dict.set_synced(addr, vec!["消した".to_string()]).unwrap();
+
+        let registered = serde_json::json!({
+            "a": {"surface": "ｔｗｒａｄｉｏ", "pronunciation": "ツイヨミ", "priority": 5},
+            "b": {"surface": "東京", "pronunciation": "トウケイ", "priority": 0},
+            "c": {"surface": "消した", "pronunciation": "ケシタ", "priority": 5},
+            "d": {"surface": "手動", "pronunciation": "シュドウ", "priority": 5},
+        });
+        let mut ops = dict.sync_ops(addr, &registered);
+        ops.sort_by_key(|op| format!("{:?}", op));
+
+        assert_eq!(
+            ops,
+            vec![
+                SyncOp::Add(word("新語", "しんご", false, 0)),
+                SyncOp::Delete("c".to_string(), "消した".to_string()),
+                SyncOp::Update("b".to_string(), word("東京", "とうきょう", false, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn ts_replace_same_surface() {
+        let mut dict = UserDict::new(None);
+        dict.add(word("草", "くさ", false, 0)).unwrap();
+        dict.add(word("草", "わら", false, 0)).unwrap();
+
+        assert_eq!(dict.words().len(), 1);
+        assert_eq!(dict.apply("草"), "わら");
+    }
+}
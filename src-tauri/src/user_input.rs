@@ -1,3 +1,11 @@
+use crate::author_rules;
+use crate::catch_up;
+use crate::mute_rules;
+use crate::priority_rules;
+use crate::recorder;
+use crate::scheduler;
+use crate::translator;
+use crate::voicegen_filter;
 use crate::voicegen_observer;
 use crate::twitter_agent;
 use serde::{Deserialize, Serialize};
@@ -5,8 +13,27 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum UserInput {
     Jump(String),
+    Replay(String),
+    RepeatLast,
+    Previous,
+    Next,
+    Skip(usize),
+    Remove(String),
+    MoveToFront(String),
+    ClearQueue,
     Paused(bool),
     Speaker(voicegen_observer::Speaker),
     SpeechRate(f64),
     TimelineView(twitter_agent::Timeline),
+    EmojiMode(voicegen_filter::EmojiMode),
+    EmojiOverrides(std::collections::HashMap<String, String>),
+    UserDictUpdated,
+    AuthorRules(author_rules::RuleMap),
+    AutoVoice(scheduler::AutoVoice),
+    MuteRules(mute_rules::MuteRules),
+    PriorityRules(priority_rules::PriorityRules),
+    LanguageVoices(std::collections::HashMap<String, voicegen_observer::Speaker>),
+    Translation(translator::TranslateSettings),
+    CatchUp(catch_up::CatchUpSettings),
+    Recording(recorder::RecordSettings),
 }
@@ -1,13 +1,18 @@
+use crate::cancel_token;
+use crate::host;
+use crate::language;
+use crate::name_cache;
 use crate::scheduler;
+use crate::translator;
 use crate::voicegen_client;
 use crate::voicegen_filter;
 use serde::{Deserialize, Serialize};
 use wana_kana::to_hiragana::*;
 
 
-use tauri::Manager;
-
 //const REQUEST_PERIOD: u64 = 3000; // milliseconds
+// Requests each engine is given at once, name and text counted apart
+const REQUESTS_PER_ENGINE: usize = 2;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Playbook {
@@ -17,6 +22,14 @@ pub struct Playbook {
     pub addr: std::net::SocketAddr,
     pub speaker: u64,
     pub speech_rate: f64,
+    pub emoji: voicegen_filter::EmojiSettings,
+    pub lang: String,
+    pub translation: Option<translator::Job>,
+    // Whether the speech may be kept for replay
+    pub cacheable: bool,
+    pub job_id: u64,
+    #[serde(skip)]
+    pub cancel: cancel_token::CancelToken,
 }
 
 pub fn into(
@@ -24,6 +37,8 @@ pub fn into(
     addr: std::net::SocketAddr,
     speaker: u64,
     speech_rate: f64,
+    emoji: voicegen_filter::EmojiSettings,
+    lang: String,
 ) -> Playbook {
     Playbook {
         tweet_id: record.tweet_id,
@@ -32,117 +47,189 @@ pub fn into(
         addr,
         speaker,
         speech_rate,
+        emoji,
+        lang,
+        translation: None,
+        cacheable: true,
+        job_id: 0,
+        cancel: cancel_token::CancelToken::new(),
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Speech {
     pub tweet_id: String,
+    pub job_id: u64,
+    // Engine that synthesized it
+    pub addr: std::net::SocketAddr,
     pub text: Vec<u8>,
     pub name: Vec<u8>,
+    pub cacheable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Failed {
+    pub tweet_id: String,
+    pub job_id: u64,
+}
+
+pub type TTSResult = Result<Speech, Failed>;
+
+async fn request_voice(
+    host: &host::Host,
+    engine: &tokio::sync::Semaphore,
+    msg: &Playbook,
+    text: &String,
+) -> Option<Vec<u8>> {
+    let resp = {
+        let _permit = engine.acquire().await.unwrap();
+        voicegen_client::request_voice(msg.addr, msg.speaker, msg.speech_rate, text).await
+    };
+
+    match resp {
+        Ok(s) => {
+            host.emit("tauri://frontend/tts-failed", "");
+            Some(s)
+        }
+        Err(e) => match e {
+            voicegen_client::RequestError::Unknown(emsg) => {
+                host.emit(
+                    "tauri://frontend/tts-failed",
+                    "音声の取得に失敗しました",
+                );
+
+                println!("voicegen_client: failed to process tts {:?}", emsg);
+                None
+            }
+        },
+    }
+}
+
+// Names are short and repeated constantly, so they are served from the cache when possible
+async fn request_name(
+    host: &host::Host,
+    engine: &tokio::sync::Semaphore,
+    msg: &Playbook,
+    text: &String,
+) -> Option<Vec<u8>> {
+    let key = match name_cache::NameCache::key(msg.addr, msg.speaker, msg.speech_rate, text) {
+        Some(key) => key,
+        None => return request_voice(host, engine, msg, text).await,
+    };
+
+    let cache = host.name_cache();
+    if let Some(voice) = cache.lock().await.get(&key) {
+        return Some(voice);
+    }
+
+    let voice = request_voice(host, engine, msg, text).await?;
+    cache.lock().await.insert(key, &voice);
+
+    Some(voice)
+}
+
+async fn synthesize(host: host::Host, engine: &tokio::sync::Semaphore, msg: Playbook) -> TTSResult {
+    let failed = Failed {
+        tweet_id: msg.tweet_id.clone(),
+        job_id: msg.job_id,
+    };
+
+    // Modify username for speech
+    let hira_name = {
+        let dict = host.user_dict().lock().await;
+        dict.apply(msg.name.as_str())
+    };
+    let hira_name = voicegen_filter::replace_emoji(hira_name.as_str(), &msg.emoji);
+    let hira_name = if msg.lang == language::JAPANESE {
+        to_hiragana(hira_name.as_str())
+    } else {
+        hira_name
+    };
+
+    // Modify tweet message for speech
+    let hira_text = voicegen_filter::replace_retweet(msg.text.as_str());
+    let hira_text = if msg.lang == language::JAPANESE {
+        voicegen_filter::replace_url(hira_text.as_str())
+    } else {
+        voicegen_filter::remove_url(hira_text.as_str())
+    };
+    let hira_text = {
+        let dict = host.user_dict().lock().await;
+        dict.apply(hira_text.as_str())
+    };
+    let hira_text = voicegen_filter::replace_emoji(hira_text.as_str(), &msg.emoji);
+    // Kana conversion garbles non Japanese text
+    let hira_text = if msg.lang == language::JAPANESE {
+        to_hiragana(hira_text.as_str())
+    } else {
+        hira_text
+    };
+
+    // Name is empty when the author is configured not to read it
+    let name_job = async {
+        if msg.name.is_empty() {
+            Some(vec![])
+        } else {
+            request_name(&host, engine, &msg, &hira_name).await
+        }
+    };
+    let text_job = request_voice(&host, engine, &msg, &hira_text);
+
+    match tokio::join!(name_job, text_job) {
+        (Some(name), Some(text)) => Ok(Speech {
+            tweet_id: msg.tweet_id,
+            job_id: msg.job_id,
+            addr: msg.addr,
+            text,
+            name,
+            cacheable: msg.cacheable,
+        }),
+
+        _ => Err(failed),
+    }
 }
 
 pub fn start(
-    app_handle: tauri::AppHandle,
+    host: host::Host,
     mut playbook_rx: tokio::sync::mpsc::Receiver<Playbook>,
-    speech_tx: tokio::sync::mpsc::Sender<Option<Speech>>,
+    speech_tx: tokio::sync::mpsc::Sender<TTSResult>,
 ) {
     // Wait while speaker detect
-    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
-    let ctx = std::sync::Mutex::new(Some(tx));
-    let id = app_handle
-        .clone()
-        .listen_global("tauri://backend/speakers-ready", move |_| {
-            let mut rdy_tx = ctx.lock().unwrap();
-            rdy_tx.take().unwrap().send(()).unwrap()
-        });
+    let rx = host.once("tauri://backend/speakers-ready");
 
     tokio::spawn(async move {
         // Wait while speaker detect
         let _ = rx.await.unwrap();
-        app_handle.unlisten(id);
+
+        // Limits concurrent requests to each engine
+        let mut engines =
+            std::collections::HashMap::<std::net::SocketAddr, std::sync::Arc<tokio::sync::Semaphore>>::new();
 
         loop {
             match playbook_rx.recv().await {
                 Some(msg) => {
-                    // Modify username for speech
-
-                    let hira_name = to_hiragana(msg.name.as_str());
-                    let resp = voicegen_client::request_voice(
-                        msg.addr,
-                        msg.speaker,
-                        msg.speech_rate,
-                        &hira_name,
-                    )
-                    .await;
-
-                    let speech_name = match resp {
-                        Ok(s) => {
-                            app_handle
-                                .emit_all("tauri://frontend/tts-failed", "")
-                                .unwrap();
-                            Some(s)
-                        }
-                        Err(e) => match e {
-                            voicegen_client::RequestError::Unknown(emsg) => {
-                                app_handle
-                                    .emit_all(
-                                        "tauri://frontend/tts-failed",
-                                        "音声の取得に失敗しました",
-                                    )
-                                    .unwrap();
-
-                                println!("voicegen_client: failed to process tts {:?}", emsg);
-                                speech_tx.send(None).await.unwrap();
-                                continue;
-                            }
-                        },
-                    };
-
-                    // Modify tweet message for speech
-                    let hira_text = voicegen_filter::replace_retweet(msg.text.as_str());
-                    let hira_text = voicegen_filter::replace_url(hira_text.as_str());
-                    let hira_text = to_hiragana(hira_text.as_str());
-
-                    let resp = voicegen_client::request_voice(
-                        msg.addr,
-                        msg.speaker,
-                        msg.speech_rate,
-                        &hira_text,
-                    )
-                    .await;
-                    let speech_text = match resp {
-                        Ok(s) => {
-                            app_handle
-                                .emit_all("tauri://frontend/tts-failed", "")
-                                .unwrap();
-                            Some(s)
-                        }
-                        Err(e) => match e {
-                            voicegen_client::RequestError::Unknown(emsg) => {
-                                app_handle
-                                    .emit_all(
-                                        "tauri://frontend/tts-failed",
-                                        "音声の取得に失敗しました",
-                                    )
-                                    .unwrap();
-
-                                println!("voicegen_client: failed to process tts {:?}", emsg);
-                                speech_tx.send(None).await.unwrap();
-                                continue;
+                    let semaphore = engines
+                        .entry(msg.addr)
+                        .or_insert_with(|| std::sync::Arc::new(tokio::sync::Semaphore::new(REQUESTS_PER_ENGINE)))
+                        .clone();
+
+                    let host = host.clone();
+                    let speech_tx = speech_tx.clone();
+                    tokio::spawn(async move {
+                        let cancel = msg.cancel.clone();
+                        let job_id = msg.job_id;
+
+                        // Dropping the job on cancel aborts its HTTP requests as well
+                        tokio::select! {
+                            result = synthesize(host, &semaphore, msg) => {
+                                let _ = speech_tx.send(result).await;
                             }
-                        },
-                    };
-
-                    println!("{:?}", hira_text);
 
-                    let speech = Speech {
-                        tweet_id: msg.tweet_id,
-                        text: speech_text.unwrap(),
-                        name: speech_name.clone().unwrap(),
-                    };
-
-                    speech_tx.send(Some(speech)).await.unwrap();
+                            _ = cancel.cancelled() => {
+                                println!("voicegen_agent: job {:?} is canceled", job_id);
+                            }
+                        }
+                    });
                 }
 
                 None => {
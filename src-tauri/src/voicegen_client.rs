@@ -87,6 +87,114 @@ pub async fn request_speakers(
     Ok(speakers_response)
 }
 
+pub async fn request_user_dict(
+    addr: std::net::SocketAddr,
+) -> Result<serde_json::Value, RequestError> {
+    let client = reqwest::Client::new();
+    let url: String = format!("http://{}/user_dict", addr);
+
+    let user_dict = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| RequestError::Unknown(e.to_string()))?;
+
+    if !user_dict.status().is_success() {
+        return Err(RequestError::Unknown(user_dict.status().to_string()));
+    }
+
+    let user_dict = user_dict
+        .text()
+        .await
+        .map_err(|e| RequestError::Unknown(e.to_string()))?;
+
+    serde_json::from_str::<serde_json::Value>(user_dict.as_str())
+        .map_err(|e| RequestError::Unknown(e.to_string()))
+}
+
+pub async fn request_add_user_dict_word(
+    addr: std::net::SocketAddr,
+    surface: &str,
+    pronunciation: &str,
+    priority: u32,
+) -> Result<String, RequestError> {
+    let client = reqwest::Client::new();
+    let url: String = format!("http://{}/user_dict_word", addr);
+
+    let resp = client
+        .post(url)
+        .query(&[
+            ("surface", surface),
+            ("pronunciation", pronunciation),
+            ("accent_type", "0"),
+            ("priority", priority.min(10).to_string().as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| RequestError::Unknown(e.to_string()))?;
+
+    if !resp.status().is_success() {
+        return Err(RequestError::Unknown(resp.status().to_string()));
+    }
+
+    // Response is the uuid of the new word
+    let uuid = resp
+        .text()
+        .await
+        .map_err(|e| RequestError::Unknown(e.to_string()))?;
+
+    Ok(uuid.trim_matches('"').to_string())
+}
+
+pub async fn request_update_user_dict_word(
+    addr: std::net::SocketAddr,
+    uuid: &str,
+    surface: &str,
+    pronunciation: &str,
+    priority: u32,
+) -> Result<(), RequestError> {
+    let client = reqwest::Client::new();
+    let url: String = format!("http://{}/user_dict_word/{}", addr, uuid);
+
+    let resp = client
+        .put(url)
+        .query(&[
+            ("surface", surface),
+            ("pronunciation", pronunciation),
+            ("accent_type", "0"),
+            ("priority", priority.min(10).to_string().as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| RequestError::Unknown(e.to_string()))?;
+
+    if !resp.status().is_success() {
+        return Err(RequestError::Unknown(resp.status().to_string()));
+    }
+
+    Ok(())
+}
+
+pub async fn request_delete_user_dict_word(
+    addr: std::net::SocketAddr,
+    uuid: &str,
+) -> Result<(), RequestError> {
+    let client = reqwest::Client::new();
+    let url: String = format!("http://{}/user_dict_word/{}", addr, uuid);
+
+    let resp = client
+        .delete(url)
+        .send()
+        .await
+        .map_err(|e| RequestError::Unknown(e.to_string()))?;
+
+    if !resp.status().is_success() {
+        return Err(RequestError::Unknown(resp.status().to_string()));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
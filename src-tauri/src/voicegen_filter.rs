@@ -1,4 +1,8 @@
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::emoji_table;
 
 pub fn replace_retweet(input: &str) -> String {
     lazy_static::lazy_static! {
@@ -15,3 +19,222 @@ pub fn replace_url(input: &str) -> String {
 
     RE.replace_all(input, "ユーアールエル略").to_string()
 }
+
+pub fn remove_url(input: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref RE: Regex = Regex::new(r"https?://[\w!\?/\+\-_~=;\.,\*&@#\$%\(\)'\[\]]+").unwrap();
+    }
+
+    RE.replace_all(input, "").to_string()
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum EmojiMode {
+    Read,
+    Drop,
+    Collapse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EmojiSettings {
+    pub mode: EmojiMode,
+    // emoji -> reading. An empty reading drops the emoji.
+    pub overrides: HashMap<String, String>,
+}
+
+impl EmojiSettings {
+    pub fn new() -> Self {
+        EmojiSettings {
+            mode: EmojiMode::Read,
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+// Strip variation selectors and skin tone modifiers for table lookup
+fn normalize_emoji(emoji: &str) -> String {
+    emoji
+        .chars()
+        .filter(|c| !matches!(c, '\u{FE0E}' | '\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}'))
+        .collect()
+}
+
+fn emoji_reading(emoji: &str, settings: &EmojiSettings) -> String {
+    lazy_static::lazy_static! {
+        static ref TABLE: HashMap<String, &'static str> = emoji_table::EMOJI_READINGS
+            .iter()
+            .map(|(k, v)| (normalize_emoji(k), *v))
+            .collect();
+    }
+
+    let key = normalize_emoji(emoji);
+    if let Some(r) = settings
+        .overrides
+        .get(emoji)
+        .or_else(|| settings.overrides.get(&key))
+    {
+        return r.clone();
+    }
+
+    if let Some(r) = TABLE.get(&key) {
+        return r.to_string();
+    }
+
+    // ZWJ sequences which are not in the table are read as their first element
+    let head: String = key.chars().take(1).collect();
+    TABLE.get(&head).map(|r| r.to_string()).unwrap_or_default()
+}
+
+// Single pass from the left, the longest kaomoji matching at each position
+// wins, so that "＼(^o^)／" is not read as "(^o^)" between backslashes.
+fn replace_kaomoji(input: &str, settings: &EmojiSettings) -> String {
+    lazy_static::lazy_static! {
+        static ref LONGEST_FIRST: Vec<(&'static str, &'static str)> = {
+            let mut table = emoji_table::KAOMOJI_READINGS.to_vec();
+            table.sort_by_key(|(kaomoji, _)| std::cmp::Reverse(kaomoji.len()));
+            table
+        };
+    }
+
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        let found = LONGEST_FIRST
+            .iter()
+            .find(|(kaomoji, _)| rest.starts_with(kaomoji));
+
+        match found {
+            Some((kaomoji, reading)) => {
+                if settings.mode != EmojiMode::Drop {
+                    output.push_str(
+                        settings
+                            .overrides
+                            .get(*kaomoji)
+                            .map(|s| s.as_str())
+                            .unwrap_or(reading),
+                    );
+                }
+                rest = &rest[kaomoji.len()..];
+            }
+            None => {
+                output.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    output
+}
+
+pub fn replace_emoji(input: &str, settings: &EmojiSettings) -> String {
+    lazy_static::lazy_static! {
+        static ref RE: Regex = Regex::new(concat!(
+            r"[\x{1F1E6}-\x{1F1FF}]{2}",
+            r"|\p{Extended_Pictographic}[\x{FE0E}\x{FE0F}\x{1F3FB}-\x{1F3FF}\x{20E3}]*",
+            r"(?:\x{200D}\p{Extended_Pictographic}[\x{FE0E}\x{FE0F}\x{1F3FB}-\x{1F3FF}]*)*",
+        ))
+        .unwrap();
+    }
+
+    let input = replace_kaomoji(input, settings);
+
+    let mut output = String::with_capacity(input.len());
+    let mut last = 0;
+    // (normalized emoji, reading, repeat count) of the current run
+    let mut run: Option<(String, String, usize)> = None;
+
+    let flush = |output: &mut String, run: &mut Option<(String, String, usize)>| {
+        if let Some((_, reading, count)) = run.take() {
+            if reading.is_empty() {
+                return;
+            }
+
+            if count > 1 {
+                output.push_str(format!("{}{}個", reading, count).as_str());
+            } else {
+                output.push_str(reading.as_str());
+            }
+        }
+    };
+
+    for m in RE.find_iter(input.as_str()) {
+        if m.start() != last {
+            flush(&mut output, &mut run);
+            output.push_str(&input[last..m.start()]);
+        }
+        last = m.end();
+
+        let reading = match settings.mode {
+            EmojiMode::Drop => continue,
+            _ => emoji_reading(m.as_str(), settings),
+        };
+
+        if settings.mode == EmojiMode::Read {
+            output.push_str(reading.as_str());
+            continue;
+        }
+
+        let key = normalize_emoji(m.as_str());
+        match run {
+            Some((ref k, _, ref mut count)) if *k == key => {
+                *count += 1;
+            }
+
+            _ => {
+                flush(&mut output, &mut run);
+                run = Some((key, reading, 1));
+            }
+        }
+    }
+
+    flush(&mut output, &mut run);
+    output.push_str(&input[last..]);
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ts_replace_emoji_read() {
+        let settings = EmojiSettings::new();
+        assert_eq!(replace_emoji("おはよう🌸", &settings), "おはよう桜");
+        assert_eq!(replace_emoji("❤️👍🏻", &settings), "赤いハートいいね");
+    }
+
+    #[test]
+    fn ts_replace_emoji_drop() {
+        let mut settings = EmojiSettings::new();
+        settings.mode = EmojiMode::Drop;
+        assert_eq!(replace_emoji("草😂😂(^^)", &settings), "草");
+    }
+
+    #[test]
+    fn ts_replace_kaomoji_longest() {
+        let settings = EmojiSettings::new();
+        assert_eq!(replace_emoji("＼(^o^)／", &settings), "ばんざい");
+        assert_eq!(replace_emoji("\\(^o^)/やった(^o^)", &settings), "ばんざいやったわーい");
+        assert_eq!(replace_emoji("ごめん(^^;)", &settings), "ごめんあせあせ");
+        assert_eq!(replace_emoji("ごめん(^^;", &settings), "ごめんあせあせ");
+    }
+
+    #[test]
+    fn ts_replace_emoji_collapse() {
+        let mut settings = EmojiSettings::new();
+        settings.mode = EmojiMode::Collapse;
+        assert_eq!(
+            replace_emoji("😂😂😂😂😂すごい🔥", &settings),
+            "泣き笑いの顔5個すごい炎"
+        );
+    }
+
+    #[test]
+    fn ts_replace_emoji_override() {
+        let mut settings = EmojiSettings::new();
+        settings.overrides.insert("😂".to_string(), "わら".to_string());
+        settings.overrides.insert("🔥".to_string(), "".to_string());
+        assert_eq!(replace_emoji("😂🔥", &settings), "わら");
+    }
+}
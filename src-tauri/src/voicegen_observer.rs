@@ -1,9 +1,8 @@
+use crate::host;
 use crate::voicegen_client;
 use crate::voicegen_data;
 use serde::{Deserialize, Serialize};
 
-use tauri::Manager;
-
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Speaker {
     pub addr: std::net::SocketAddr,
@@ -37,7 +36,9 @@ impl Speaker {
 
 const OFFSET_TIME: u64 = 3000;
 
-pub fn start(app_handle: tauri::AppHandle) {
+pub fn start(host: host::Host) -> tokio::sync::mpsc::Receiver<Vec<Speaker>> {
+    let (speakers_tx, speakers_rx) = tokio::sync::mpsc::channel::<Vec<Speaker>>(1);
+
     let addrs = vec![
         (
             "VOICEVOX",
@@ -62,20 +63,12 @@ pub fn start(app_handle: tauri::AppHandle) {
     ];
 
     // Wait while speaker detect
-    let (wait_tx, wait_rx) = tokio::sync::oneshot::channel::<()>();
-    let ctx = std::sync::Mutex::new(Some(wait_tx));
-    let id = app_handle
-        .clone()
-        .listen_global("tauri://backend/ipc-init", move |_| {
-            let mut rdy_tx = ctx.lock().unwrap();
-            rdy_tx.take().unwrap().send(()).unwrap()
-        });
+    let wait_rx = host.once("tauri://backend/ipc-init");
 
     // ipc-init is called once by frontend
     tokio::spawn(async move {
         // Wait while speaker detect
         let _ = wait_rx.await.unwrap();
-        app_handle.unlisten(id);
 
         // Main loop
         let mut latest_vec = Vec::<Speaker>::new();
@@ -99,30 +92,38 @@ pub fn start(app_handle: tauri::AppHandle) {
             }
 
             if vec != latest_vec {
+                // The engine was updated or replaced, so cached voices may sound different now
+                for (_, addr) in &addrs {
+                    let before: Vec<&Speaker> = latest_vec.iter().filter(|s| s.addr == *addr).collect();
+                    let after: Vec<&Speaker> = vec.iter().filter(|s| s.addr == *addr).collect();
+                    if !before.is_empty() && !after.is_empty() && before != after {
+                        host
+                            .name_cache()
+                            .lock()
+                            .await
+                            .invalidate_engine(*addr);
+                    }
+                }
+
                 latest_vec = vec.clone();
-                app_handle
-                    .emit_all("tauri://frontend/speakers-register", vec.clone())
-                    .unwrap();
+                let _ = speakers_tx.send(vec.clone()).await;
+                host.emit("tauri://frontend/speakers-register", vec.clone());
 
-                app_handle
-                    .emit_all("tauri://frontend/speakers-ready", ())
-                    .unwrap();
+                host.emit("tauri://frontend/speakers-ready", ());
             }
 
             if vec.len() == 0 {
-                app_handle
-                    .emit_all(
-                        "tauri://frontend/no-voicegen-found",
-                        "TTSエンジンを起動してください",
-                    )
-                    .unwrap();
+                host.emit(
+                    "tauri://frontend/no-voicegen-found",
+                    "TTSエンジンを起動してください",
+                );
             } else {
-                app_handle
-                    .emit_all("tauri://frontend/no-voicegen-found", "")
-                    .unwrap();
+                host.emit("tauri://frontend/no-voicegen-found", "");
             }
 
             tokio::time::sleep(tokio::time::Duration::from_millis(OFFSET_TIME)).await;
         }
     });
+
+    speakers_rx
 }
@@ -0,0 +1,194 @@
+// 16 bit PCM WAV, which is what the voice engines return.
+
+pub const HEADER_LENGTH: usize = 44;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wav {
+    pub channels: u16,
+    pub sample_rate: u32,
+    // Interleaved by channel
+    pub samples: Vec<i16>,
+}
+
+fn u16_at(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+// Canonical header, the layout written by header()
+pub fn parse_header(data: &[u8]) -> Option<(u16, u32, u32)> {
+    if data.len() < HEADER_LENGTH
+        || &data[0..4] != b"RIFF"
+        || &data[8..16] != b"WAVEfmt "
+        || &data[36..40] != b"data"
+        || u16_at(data, 20) != 1
+        || u16_at(data, 34) != 16
+    {
+        return None;
+    }
+
+    Some((u16_at(data, 22), u32_at(data, 24), u32_at(data, 40)))
+}
+
+pub fn header(channels: u16, sample_rate: u32, data_len: u32) -> Vec<u8> {
+    let block_align = channels * 2;
+
+    let mut h = Vec::with_capacity(HEADER_LENGTH);
+    h.extend_from_slice(b"RIFF");
+    h.extend_from_slice(&(36 + data_len).to_le_bytes());
+    h.extend_from_slice(b"WAVEfmt ");
+    h.extend_from_slice(&16u32.to_le_bytes());
+    h.extend_from_slice(&1u16.to_le_bytes());
+    h.extend_from_slice(&channels.to_le_bytes());
+    h.extend_from_slice(&sample_rate.to_le_bytes());
+    h.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    h.extend_from_slice(&block_align.to_le_bytes());
+    h.extend_from_slice(&16u16.to_le_bytes());
+    h.extend_from_slice(b"data");
+    h.extend_from_slice(&data_len.to_le_bytes());
+    h
+}
+
+impl Wav {
+    pub fn parse(data: &[u8]) -> Option<Wav> {
+        if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+            return None;
+        }
+
+        let mut format = None;
+        let mut samples = None;
+        let mut pos = 12;
+        while pos + 8 <= data.len() {
+            let size = u32_at(data, pos + 4) as usize;
+            // Streamed files leave the size unknown
+            let body = &data[pos + 8..(pos + 8).saturating_add(size).min(data.len())];
+
+            match &data[pos..pos + 4] {
+                b"fmt " => {
+                    if body.len() < 16 || u16_at(body, 0) != 1 || u16_at(body, 14) != 16 {
+                        println!("wav: unsupported format");
+                        return None;
+                    }
+                    format = Some((u16_at(body, 2), u32_at(body, 4)));
+                }
+
+                b"data" => {
+                    samples = Some(
+                        body.chunks_exact(2)
+                            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+                            .collect::<Vec<i16>>(),
+                    );
+                }
+
+                _ => {}
+            }
+
+            pos = pos.saturating_add(8 + size + (size & 1));
+        }
+
+        let (channels, sample_rate) = format?;
+        if channels == 0 || sample_rate == 0 {
+            return None;
+        }
+
+        Some(Wav {
+            channels,
+            sample_rate,
+            samples: samples?,
+        })
+    }
+
+    pub fn silence(channels: u16, sample_rate: u32, duration: std::time::Duration) -> Wav {
+        let frames = (duration.as_secs_f64() * sample_rate as f64) as usize;
+
+        Wav {
+            channels,
+            sample_rate,
+            samples: vec![0; frames * channels as usize],
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.frames() as f64 / self.sample_rate as f64
+    }
+
+    pub fn data(&self) -> Vec<u8> {
+        self.samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data = self.data();
+
+        let mut bytes = header(self.channels, self.sample_rate, data.len() as u32);
+        bytes.extend(data);
+        bytes
+    }
+
+    // Channels are mixed down and copied back, rates are interpolated linearly,
+    // which is plenty for speech.
+    pub fn convert(&self, channels: u16, sample_rate: u32) -> Wav {
+        if self.channels == channels && self.sample_rate == sample_rate {
+            return self.clone();
+        }
+
+        let mono: Vec<f64> = self
+            .samples
+            .chunks_exact(self.channels as usize)
+            .map(|frame| frame.iter().map(|s| *s as f64).sum::<f64>() / frame.len() as f64)
+            .collect();
+
+        let frames = (mono.len() as u64 * sample_rate as u64 / self.sample_rate as u64) as usize;
+        let step = self.sample_rate as f64 / sample_rate as f64;
+
+        let mut samples = Vec::with_capacity(frames * channels as usize);
+        for i in 0..frames {
+            let t = i as f64 * step;
+            let j = t as usize;
+            let a = mono[j.min(mono.len() - 1)];
+            let b = mono[(j + 1).min(mono.len() - 1)];
+            let s = (a + (b - a) * (t - j as f64)).round() as i16;
+
+            for _ in 0..channels {
+                samples.push(s);
+            }
+        }
+
+        Wav {
+            channels,
+            sample_rate,
+            samples,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ts_wav_roundtrip() {
+        let wav = Wav {
+            channels: 2,
+            sample_rate: 24000,
+            samples: vec![0, 100, -100, 200, 300, -300],
+        };
+
+        let bytes = wav.to_bytes();
+        assert_eq!(parse_header(&bytes), Some((2, 24000, 12)));
+        assert_eq!(Wav::parse(&bytes), Some(wav.clone()));
+
+        let mono = wav.convert(1, 48000);
+        assert_eq!(mono.frames(), 6);
+        assert_eq!(mono.samples, vec![50, 50, 50, 25, 0, 0]);
+        assert!((mono.duration() - wav.duration()).abs() < 1e-9);
+
+        assert!(Wav::parse(b"RIFF\0\0\0\0WAVE").is_none());
+    }
+}
@@ -29,6 +29,29 @@ type ViewElements = {
   username: string;
   profile_image_url: string;
   attachments: [string,string][];
+  muted: boolean;
+  priority: boolean;
+};
+
+type Translation = {
+  tweet_id: string;
+  text: string;
+};
+
+type Move = {
+  tweet_id: string;
+  after: string | null;
+};
+
+// Places the tweet right after another one, or first without one
+const moveTweet = <T extends { tweet_id: string }>(list: T[], data: Move) => {
+  const index = list.findIndex((elem) => elem.tweet_id === data.tweet_id);
+  if (index < 0) {
+    return;
+  }
+  const [elem] = list.splice(index, 1);
+  const after = list.findIndex((elem) => elem.tweet_id === data.after);
+  list.splice(after + 1, 0, elem);
 };
 
 function App() {
@@ -153,6 +176,8 @@ function App() {
         tweet: data.text,
         profile_image_url: data.profile_image_url,
         attachments: data.attachments,
+        muted: data.muted,
+        priority: data.priority,
       });
       setTweetList([...tweetList]);
     });
@@ -164,6 +189,11 @@ function App() {
       setTweetList([...tweetList]);
     });
 
+    listen<Move>("tauri://frontend/display/user/move", (event) => {
+      moveTweet(tweetList, event.payload);
+      setTweetList([...tweetList]);
+    });
+
     listen<string>("tauri://frontend/display/user/scroll", (event) => {
       const twid: string = event.payload;
       setFocusTweetId(twid);
@@ -181,6 +211,8 @@ function App() {
         tweet: data.text,
         profile_image_url: data.profile_image_url,
         attachments: data.attachments,
+        muted: data.muted,
+        priority: data.priority,
       });
       setSearchTweetList([...searchTweetList]);
     });
@@ -192,6 +224,23 @@ function App() {
       setSearchTweetList([...searchTweetList]);
     });
 
+    listen<Move>("tauri://frontend/display/search/move", (event) => {
+      moveTweet(searchTweetList, event.payload);
+      setSearchTweetList([...searchTweetList]);
+    });
+
+    listen<Translation>("tauri://frontend/display/translation", (event) => {
+      const data: Translation = event.payload;
+      for (const list of [tweetList, searchTweetList]) {
+        const elem = list.find((elem) => elem.tweet_id === data.tweet_id);
+        if (elem) {
+          elem.translation = data.text;
+        }
+      }
+      setTweetList([...tweetList]);
+      setSearchTweetList([...searchTweetList]);
+    });
+
     listen<string>("tauri://frontend/display/search/scroll", (event) => {
       const twid: string = event.payload;
       setSearchFocusTweetId(twid);
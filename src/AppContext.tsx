@@ -17,6 +17,7 @@ type AppContextType = {
   speakerPair: [string, React.Dispatch<string>];
   speakerListPair: [Array<SpeakerInfo>, React.Dispatch<Array<SpeakerInfo>>];
   speechRatePair: [number, React.Dispatch<number>];
+  playbackSpeedPair: [number, React.Dispatch<number>];
 };
 
 export const AppContext = createContext({} as AppContextType);
@@ -87,6 +88,21 @@ export const AppContextProvider = ({ children }: { children: ReactNode }) => {
     localStorage.setItem("speechRate", JSON.stringify(speechRate as number));
   }, [speechRate]);
 
+  // Applied as played, unlike the speech rate nothing is synthesized again
+  const [playbackSpeed, setPlaybackSpeed] = React.useState(() => {
+    const json = localStorage.getItem("playbackSpeed");
+    const parsedInitPlaybackSpeed = json === null ? null : JSON.parse(json);
+    const initPlaybackSpeed =
+      parsedInitPlaybackSpeed === null ? 1.0 : parsedInitPlaybackSpeed;
+
+    return initPlaybackSpeed;
+  });
+
+  React.useEffect(() => {
+    invoke("set_playback_speed", { speed: playbackSpeed });
+    localStorage.setItem("playbackSpeed", JSON.stringify(playbackSpeed as number));
+  }, [playbackSpeed]);
+
   return (
     <AppContext.Provider
       value={{
@@ -99,6 +115,7 @@ export const AppContextProvider = ({ children }: { children: ReactNode }) => {
         speakerPair: [speaker, setSpeaker],
         speakerListPair: [speakerList, setSpeakerList],
         speechRatePair: [speechRate, setSpeechRate],
+        playbackSpeedPair: [playbackSpeed, setPlaybackSpeed],
       }}
     >
       {children}
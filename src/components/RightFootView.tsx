@@ -34,6 +34,15 @@ export const RightFoot = () => {
         console.log(errmsg);
     });
 
+    const [audioErr, setAudioErr] = React.useState<string>(()=>{ return ""; });
+
+    listen<string>('tauri://frontend/audio-failed', (event)=> {
+        const errmsg: string = event.payload;
+        setAudioErr(errmsg);
+
+        console.log(errmsg);
+    });
+
     const [otherErr, setOtherErr] = React.useState<string>(()=>{ return ""; });
 
     listen<string>('tauri://frontend/other-error', (event)=> {
@@ -57,6 +66,9 @@ export const RightFoot = () => {
             {
                 TTSErr !== "" ? <Alert severity="warning">{TTSErr}</Alert> :<></>
             }
+            {
+                audioErr !== "" ? <Alert severity="warning">{audioErr}</Alert> :<></>
+            }
             <Alert severity="info">バグ報告等 Twitter @tapoh22334</Alert>
         </Box>
     );
@@ -81,6 +81,9 @@ export const SearchView = ({tweets}: {tweets: Array<TweetProps>}) => {
                                   tweet={row.tweet}
                                   profile_image_url={row.profile_image_url}
                                   attachments={row.attachments}
+                                  muted={row.muted}
+                                  priority={row.priority}
+                                  translation={row.translation}
                                   focus={false}
                                   />
                               <Divider component="li" />
@@ -25,10 +25,11 @@ export const toUniqueSpeakerId = (speaker: SpeakerInfo) => {
 };
 
 export const Settings = () => {
-    const {speakerPair, speakerListPair, speechRatePair} = React.useContext(AppContext);
+    const {speakerPair, speakerListPair, speechRatePair, playbackSpeedPair} = React.useContext(AppContext);
     const [speaker, setSpeaker] = speakerPair;
     const [speakerList, setSpeakerList] = speakerListPair;
     const [speechRate, setSpeechRate] = speechRatePair;
+    const [playbackSpeed, setPlaybackSpeed] = playbackSpeedPair;
 
     const onSpeakerChange = (event: SelectChangeEvent) => {
         const value = event.target.value as string
@@ -49,6 +50,10 @@ export const Settings = () => {
         setSpeechRate(value as number)
     }
 
+    const onPlaybackSpeedChange = (_: Event, value: number | number[]) => {
+        setPlaybackSpeed(value as number)
+    }
+
     return (
         <Box>
             <Box margin={2}>
@@ -86,6 +91,20 @@ export const Settings = () => {
                   onChange={onSpeechRateChange}
                 />
             </Box>
+
+            <Box margin={2}>
+                <Typography gutterBottom>
+                  再生速度
+                </Typography>
+                <Slider
+                  step={0.1}
+                  min={0.5}
+                  max={3.0}
+                  valueLabelDisplay="auto"
+                  value={playbackSpeed}
+                  onChange={onPlaybackSpeedChange}
+                />
+            </Box>
         </Box>
     );
 
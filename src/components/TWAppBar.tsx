@@ -12,6 +12,9 @@ import IconButton from '@mui/material/IconButton';
 import PauseRounded from '@mui/icons-material/PauseRounded';
 import PlayArrowRounded from '@mui/icons-material/PlayArrowRounded';
 import FastForwardRounded from '@mui/icons-material/FastForwardRounded';
+import SkipPreviousRounded from '@mui/icons-material/SkipPreviousRounded';
+import ReplayRounded from '@mui/icons-material/ReplayRounded';
+import ClearAllRounded from '@mui/icons-material/ClearAllRounded';
 import CenterFocusStrongIcon from '@mui/icons-material/CenterFocusStrong';
 import CenterFocusWeakIcon from '@mui/icons-material/CenterFocusWeak';
 
@@ -33,6 +36,18 @@ export const TWAppBar = () => {
     setSkipped(true);
   }
 
+  const onPreviousClick = () => {
+    invoke('previous');
+  }
+
+  const onRepeatClick = () => {
+    invoke('repeat_last');
+  }
+
+  const onClearQueueClick = () => {
+    invoke('clear_queue');
+  }
+
   const onFocusClick = () => {
     setFocused(!focused);
   }
@@ -72,12 +87,30 @@ export const TWAppBar = () => {
               {paused ? <PlayArrowRounded /> : <PauseRounded />}
           </IconButton>
 
+          <IconButton
+              color="inherit"
+              onClick={onPreviousClick}>
+              <SkipPreviousRounded />
+          </IconButton>
+
+          <IconButton
+              color="inherit"
+              onClick={onRepeatClick}>
+              <ReplayRounded />
+          </IconButton>
+
           <IconButton
               color="inherit"
               onClick={onSkipClick}>
               <FastForwardRounded />
           </IconButton>
 
+          <IconButton
+              color="inherit"
+              onClick={onClearQueueClick}>
+              <ClearAllRounded />
+          </IconButton>
+
           <IconButton
               color="inherit"
               onClick={onFocusClick}>
@@ -1,6 +1,7 @@
 import './TweetCard.css';
 
 import { emit } from "@tauri-apps/api/event";
+import { invoke } from '@tauri-apps/api'
 
 import * as React from 'react';
 import ListItem from '@mui/material/ListItem';
@@ -11,6 +12,9 @@ import Typography from '@mui/material/Typography';
 import Box from "@mui/material/Box";
 import ImageList from '@mui/material/ImageList';
 import ImageListItem from '@mui/material/ImageListItem';
+import IconButton from '@mui/material/IconButton';
+import VerticalAlignTopRounded from '@mui/icons-material/VerticalAlignTopRounded';
+import CloseRounded from '@mui/icons-material/CloseRounded';
 
 
 export type TweetProps = {
@@ -22,6 +26,9 @@ export type TweetProps = {
     tweet: string,
     profile_image_url: string,
     attachments: [string,string][];
+    muted: boolean;
+    priority: boolean;
+    translation?: string;
 }
 
 export type TweetLiProps = {
@@ -33,6 +40,9 @@ export type TweetLiProps = {
     tweet: string,
     profile_image_url: string,
     attachments: [string,string][],
+    muted: boolean,
+    priority: boolean,
+    translation?: string,
     focus: boolean,
 }
 
@@ -109,6 +119,12 @@ const TweetLiText: React.FC<TweetLiProps> = (props) => {
                 <Box>
                     {props.tweet}
                 </Box>
+                {
+                    props.translation &&
+                        <Box sx={{ marginTop: "4px", fontStyle: "italic" }}>
+                            {props.translation}
+                        </Box>
+                }
                 <Box>
                     <ImageList >
                       {
@@ -135,9 +151,29 @@ const TweetLiText: React.FC<TweetLiProps> = (props) => {
     );
 }
 
+const QueueActions: React.FC<{tweet_id: string}> = (props) => {
+    return (
+        <Box>
+            <IconButton size="small" onClick={ () => { invoke('move_to_front', {twid: props.tweet_id}) } }>
+                <VerticalAlignTopRounded fontSize="small" />
+            </IconButton>
+            <IconButton size="small" onClick={ () => { invoke('remove_tweet', {twid: props.tweet_id}) } }>
+                <CloseRounded fontSize="small" />
+            </IconButton>
+        </Box>
+    );
+}
+
 export const TweetLi: React.FC<TweetLiProps> = (props) => {
     return (
-          <ListItem id={props.tweet_id}  alignItems="flex-start" sx={{margin: "4px"}}>
+          <ListItem id={props.tweet_id}  alignItems="flex-start"
+            secondaryAction={ props.muted ? undefined : <QueueActions tweet_id={props.tweet_id} /> }
+            sx={{
+              margin: "4px",
+              opacity: props.muted ? 0.4 : 1,
+              border: props.priority ? "1px solid #1d9bf0" : "none",
+              borderRadius: "8px",
+            }}>
             <ListItemAvatar>
               <Avatar src={props.profile_image_url} />
             </ListItemAvatar>